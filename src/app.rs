@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::Parser;
 
 #[derive(Parser, Debug)]
@@ -14,6 +16,8 @@ pub enum Subcommand {
         version: Option<String>,
         #[clap(short, long, default_value = "10G")]
         mem: String,
+        #[clap(flatten)]
+        launch: LaunchOptions,
     },
     Fabric {
         #[clap(short, long)]
@@ -22,6 +26,8 @@ pub enum Subcommand {
         loader_version: Option<String>,
         #[clap(short, long, default_value = "10G")]
         mem: String,
+        #[clap(flatten)]
+        launch: LaunchOptions,
     },
     Quilt {
         #[clap(short, long)]
@@ -32,6 +38,8 @@ pub enum Subcommand {
         mem: String,
         #[clap(short, long, default_value = "false")]
         use_release: bool,
+        #[clap(flatten)]
+        launch: LaunchOptions,
     },
     Liteloader {
         #[clap(short, long)]
@@ -40,6 +48,91 @@ pub enum Subcommand {
         loader_version: Option<String>,
         #[clap(short, long, default_value = "10G")]
         mem: String,
+        #[clap(flatten)]
+        launch: LaunchOptions,
+    },
+    #[command(about = "Install and launch a version JSON, which may inherit from a vanilla version (forge, liteloader...)")]
+    Custom {
+        #[clap(short, long)]
+        json: PathBuf,
+        #[clap(short, long, default_value = "10G")]
+        mem: String,
+        #[clap(flatten)]
+        launch: LaunchOptions,
+    },
+    #[command(about = "Install and run dedicated servers")]
+    Server {
+        #[command(subcommand)]
+        command: ServerCommand,
+    },
+    #[command(about = "Runs commands on a server over rcon, interactively if no command is given")]
+    Rcon {
+        #[clap(short, long, conflicts_with = "server", help = "host[:port], defaults to the rcon address of the server managed by mc_cli")]
+        address: Option<String>,
+        command: Vec<String>,
+        #[clap(short, long)]
+        password: Option<String>,
+        #[clap(short, long, help = "The managed server to read the rcon address and password from")]
+        server: Option<String>,
+    },
+    #[command(about = "Shows the MOTD, version and players of any server")]
+    Ping {
+        #[clap(help = "host[:port], the port is looked up through the _minecraft._tcp SRV record if left out")]
+        address: String,
+        #[clap(long, default_value = "false")]
+        json: bool,
+    },
+    #[command(about = "Shows the full info and player list of a server with enable-query on")]
+    Query {
+        #[clap(help = "host[:port], defaults to the query address of the server managed by mc_cli")]
+        address: Option<String>,
+        #[clap(long, default_value = "false", help = "Only do the basic stat request")]
+        basic: bool,
+        #[clap(long, default_value = "false")]
+        json: bool,
+        #[clap(short, long, help = "The managed server to read the query port from")]
+        server: Option<String>,
+    },
+    #[command(about = "Reads and edits the launch defaults of an installed version")]
+    Instance {
+        #[command(subcommand)]
+        command: InstanceCommand,
+    },
+    #[command(about = "Writes a script which launches an installed version without mc_cli")]
+    ExportLaunchScript {
+        instance: String,
+        #[clap(short, long, help = "Defaults to <instance>.sh, or <instance>.bat on Windows")]
+        output: Option<PathBuf>,
+        #[clap(long, value_enum, help = "Defaults to the one matching the output's extension")]
+        format: Option<ScriptFormat>,
+        #[clap(short, long, default_value = "10G")]
+        mem: String,
+        #[clap(flatten)]
+        launch: LaunchOptions,
+    },
+    #[command(about = "Lists the games running in the background")]
+    Ps,
+    #[command(about = "Prints the log of the last launch of an instance, or lists, shows and searches older logs", args_conflicts_with_subcommands = true)]
+    Logs {
+        #[command(subcommand)]
+        command: Option<LogsCommand>,
+        instance: Option<String>,
+        #[clap(short, long, default_value = "false", help = "Keeps printing new output until the game exits")]
+        follow: bool,
+        #[clap(long, value_enum, default_value = "info")]
+        log_level: LogLevel,
+    },
+    #[command(about = "Stops a game running in the background")]
+    Kill {
+        instance: String,
+    },
+    #[command(
+        about = "Deletes the libraries, assets and natives no installed version uses",
+        long_about = "Deletes the libraries, assets and natives no installed version uses.\n\nThere are no Java runtimes to collect, mc_cli runs the `java` on PATH and doesn't install any."
+    )]
+    Gc {
+        #[clap(long, default_value = "false", help = "Only shows what would be deleted")]
+        dry_run: bool,
     },
     #[command(about = "List versions")]
     Versions,
@@ -50,6 +143,62 @@ pub enum Subcommand {
     },
 }
 
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct LaunchOptions {
+    #[clap(long, conflicts_with_all = ["world", "realm"], help = "Joins a server (host[:port]) right after the game starts")]
+    pub join: Option<String>,
+    #[clap(long, conflicts_with_all = ["join", "realm"], help = "Opens a singleplayer world right after the game starts")]
+    pub world: Option<String>,
+    #[clap(long, conflicts_with_all = ["join", "world"], help = "Joins a realm by its id right after the game starts")]
+    pub realm: Option<String>,
+    #[clap(long, help = "Window width, defaults to the instance's width or 854")]
+    pub width: Option<u32>,
+    #[clap(long, help = "Window height, defaults to the instance's height or 480")]
+    pub height: Option<u32>,
+    #[clap(long, default_value = "false", overrides_with = "no_fullscreen")]
+    pub fullscreen: bool,
+    #[clap(long, default_value = "false", overrides_with = "fullscreen", help = "Starts the game windowed, even if the instance defaults to fullscreen")]
+    pub no_fullscreen: bool,
+    #[clap(long, default_value = "false", overrides_with = "no_demo", help = "Starts the game in demo mode")]
+    pub demo: bool,
+    #[clap(long, default_value = "false", overrides_with = "demo", help = "Starts the full game, even if the instance defaults to demo mode")]
+    pub no_demo: bool,
+    #[clap(long, value_enum, default_value = "info", help = "The lowest level of game log events to show")]
+    pub log_level: LogLevel,
+    #[clap(long, help = "Also appends the game log to this file, without colours")]
+    pub log_file: Option<PathBuf>,
+    #[clap(long, default_value = "false", help = "Runs the game in the background, see `mc_cli ps`")]
+    pub detach: bool,
+    #[clap(long = "wrapper", help = "Runs the JVM through a command like gamemoderun, can be given more than once")]
+    pub wrappers: Vec<String>,
+    #[clap(long, value_parser = parse_env, help = "Sets an environment variable (KEY=VALUE) for the game, can be given more than once")]
+    pub env: Vec<(String, String)>,
+    #[clap(long, help = "A shell command to run before the game starts, the launch is cancelled if it fails")]
+    pub pre_launch: Vec<String>,
+    #[clap(long, help = "A shell command to run after the game exits")]
+    pub post_exit: Vec<String>,
+    #[clap(long, default_value = "false", help = "Prints the command, working dir and environment of the launch instead of running it")]
+    pub dry_run: bool,
+}
+
+fn parse_env(text: &str) -> Result<(String, String), String> {
+    match text.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        _ => Err(format!("{} isn't KEY=VALUE", text)),
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
 #[derive(clap::Subcommand, Debug)]
 pub enum OpenTarget {
     #[command(about = "Opens the game directory")]
@@ -71,3 +220,119 @@ pub enum OpenTarget {
     #[command(about = "Opens options.txt")]
     McOptions,
 }
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ServerCommand {
+    #[command(about = "Installs a dedicated server")]
+    Install {
+        version: String,
+        #[clap(short, long)]
+        loader: Option<ServerLoader>,
+        #[clap(long)]
+        loader_version: Option<String>,
+        #[clap(short, long)]
+        name: Option<String>,
+        #[clap(long, default_value = "false")]
+        accept_eula: bool,
+    },
+    #[command(about = "Starts an installed server")]
+    Start {
+        #[clap(short, long)]
+        name: Option<String>,
+        #[clap(short, long, default_value = "4G")]
+        mem: String,
+        #[clap(long, default_value = "false")]
+        accept_eula: bool,
+    },
+    #[command(about = "Stops a running server")]
+    Stop {
+        #[clap(short, long)]
+        name: Option<String>,
+    },
+    #[command(about = "Reads and edits server.properties")]
+    Config {
+        #[command(subcommand)]
+        command: ServerConfigCommand,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum ServerConfigCommand {
+    #[command(about = "Prints the value of a key, or its default if it isn't set")]
+    Get {
+        key: String,
+        #[clap(short, long)]
+        name: Option<String>,
+    },
+    #[command(about = "Sets a key, validating the value of known keys")]
+    Set {
+        key: String,
+        value: String,
+        #[clap(short, long)]
+        name: Option<String>,
+    },
+    #[command(about = "Lists every key along with the defaults of missing ones")]
+    List {
+        #[clap(short, long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum LogsCommand {
+    #[command(about = "Lists the launch logs of every instance, or of one")]
+    List {
+        instance: Option<String>,
+        #[clap(long, default_value = "false", help = "Lists the game's own logs instead")]
+        game: bool,
+    },
+    #[command(about = "Prints a log, gzipped or not")]
+    Show {
+        #[clap(help = "A name from `mc_cli logs list` or a path")]
+        name: String,
+        #[clap(long, value_enum, default_value = "trace")]
+        log_level: LogLevel,
+    },
+    #[command(about = "Searches logs for a regex")]
+    Search {
+        pattern: String,
+        instance: Option<String>,
+        #[clap(long, default_value = "false", help = "Searches the game's own logs instead")]
+        game: bool,
+        #[clap(short, long, default_value = "false")]
+        ignore_case: bool,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum InstanceCommand {
+    #[command(about = "Prints the value of a setting")]
+    Get {
+        instance: String,
+        key: String,
+    },
+    #[command(about = "Sets a setting, the value is read as JSON if it can be")]
+    Set {
+        instance: String,
+        key: String,
+        value: String,
+    },
+    #[command(about = "Lists every setting")]
+    List {
+        instance: String,
+    },
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum ScriptFormat {
+    /// a POSIX shell script
+    Sh,
+    /// a Windows batch file
+    Bat,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum ServerLoader {
+    Fabric,
+    Quilt,
+}
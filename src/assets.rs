@@ -1,10 +1,18 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fs, io, path::{Path, PathBuf}};
 
 use serde::Deserialize;
 
+use crate::util;
+
 #[derive(Deserialize, Debug)]
 pub struct AssetIndexJson {
-    pub objects: HashMap<String, Object>
+    pub objects: HashMap<String, Object>,
+    /// the legacy index (1.6 to 1.7.2), the game reads the assets by name from assets/virtual/legacy
+    #[serde(default)]
+    pub r#virtual: bool,
+    /// the pre-1.6 index, the game reads the assets by name from the resources dir in the game dir
+    #[serde(default)]
+    pub map_to_resources: bool,
 }
 
 #[derive(Deserialize, Debug)]
@@ -12,3 +20,59 @@ pub struct Object {
     pub hash: String,
     pub size: i32,
 }
+
+/// where the index with the id from the version's `assetIndex` is saved
+pub fn index_path(assets_dir: &Path, id: &str) -> PathBuf {
+    assets_dir.join("indexes").join(format!("{}.json", id))
+}
+
+pub fn read_index(assets_dir: &Path, id: &str) -> Option<AssetIndexJson> {
+    let text = fs::read_to_string(index_path(assets_dir, id)).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+/// objects are stored by their hash, under a dir named after its first two characters
+pub fn object_path(assets_dir: &Path, hash: &str) -> PathBuf {
+    assets_dir.join("objects").join(&hash[..2]).join(hash)
+}
+
+/// whether the object is already downloaded, checking its size first since hashing every object takes a while
+pub fn has_object(assets_dir: &Path, object: &Object) -> bool {
+    let path = object_path(assets_dir, &object.hash);
+    fs::metadata(&path).is_ok_and(|metadata| metadata.len() == object.size as u64) && util::file_matches_sha1(&path, &object.hash)
+}
+
+/// the dir the game gets as `${game_assets}`, the one old versions read their assets from by name
+pub fn game_assets_dir(assets_dir: &Path, id: &str, game_dir: &Path) -> PathBuf {
+    match read_index(assets_dir, id) {
+        Some(index) if index.map_to_resources => game_dir.join("resources"),
+        Some(index) if index.r#virtual => assets_dir.join("virtual").join(id),
+        _ => assets_dir.to_path_buf(),
+    }
+}
+
+/// copies the objects of a `virtual` or `map_to_resources` index to their names, which is where versions
+/// before 1.7.3 look for them. Files that are already there with the right size are left alone.
+pub fn lay_out_legacy(assets_dir: &Path, id: &str, game_dir: &Path) -> io::Result<()> {
+    let Some(index) = read_index(assets_dir, id) else { return Ok(()) };
+
+    let mut targets = vec![];
+    if index.r#virtual {
+        targets.push(assets_dir.join("virtual").join(id));
+    }
+    if index.map_to_resources {
+        targets.push(game_dir.join("resources"));
+    }
+
+    for target in targets {
+        for (name, object) in &index.objects {
+            let path = target.join(name);
+            if fs::metadata(&path).is_ok_and(|metadata| metadata.len() == object.size as u64) {
+                continue;
+            }
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::copy(object_path(assets_dir, &object.hash), &path)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use std::{fs, path::{Path, PathBuf}, time::SystemTime};
+
+use regex::Regex;
+
+/// Known problems, matched against the crash report or the end of the output
+const KNOWN_ISSUES: &[(&str, &str)] = &[
+    (
+        r"UnsupportedClassVersionError|has been compiled by a more recent version of the Java Runtime",
+        "Wrong Java version: the game or a mod needs a newer Java than the `java` on PATH",
+    ),
+    (
+        r"Unrecognized VM option|Could not create the Java Virtual Machine|Invalid maximum heap size",
+        "The JVM rejected its arguments, check the memory limit and any custom JVM arguments",
+    ),
+    (
+        r"OutOfMemoryError|Could not reserve enough space|Java heap space|insufficient memory for the Java Runtime",
+        "Out of memory: raise the limit with --mem, or lower it if the machine doesn't have that much free",
+    ),
+    (
+        r"UnsatisfiedLinkError|no lwjgl\w* in java.library.path|Failed to locate library: \S*lwjgl|Failed to load native library",
+        "Missing natives: the LWJGL native libraries couldn't be loaded, try reinstalling the version",
+    ),
+    (
+        r"GLFW error 65542|GLFW error 65543|WGL: The driver does not appear to support OpenGL|Pixel format not accelerated|GLXBadFBConfig|OpenGL 3\.2|Couldn't set pixel format",
+        "OpenGL unsupported: update the graphics drivers, or make sure the game runs on the dedicated GPU",
+    ),
+    (
+        r"(?s)(EXCEPTION_ACCESS_VIOLATION|SIGSEGV).*(atio6axx|atioglxx|nvoglv|ig\w+icd|libnvidia|iris_dri|radeonsi)",
+        "The graphics driver crashed: update it, and remove shader or rendering mods to narrow it down",
+    ),
+    (
+        r"MixinApplyError|Mixin apply failed|InvalidInjectionException|MixinTransformerError",
+        "A mod's mixin failed to apply: it's probably made for another game or loader version, or conflicts with another mod",
+    ),
+    (
+        r"Incompatible mods? found|ModResolutionException|requires (version )?\S+ of \S+, which is missing",
+        "Mod dependencies aren't met: a mod needs another mod, or a different version of one",
+    ),
+    (
+        r"NoClassDefFoundError|ClassNotFoundException",
+        "A class is missing: a mod is probably made for another game version, or misses a dependency",
+    ),
+];
+
+/// packages that show up in every stack trace and don't point at a mod
+const IGNORED_PACKAGES: &[&str] = &[
+    "java.", "javax.", "jdk.", "sun.", "com.sun.", "net.minecraft.", "com.mojang.", "net.fabricmc.", "org.quiltmc.",
+    "org.spongepowered.", "cpw.", "net.minecraftforge.", "net.neoforged.", "com.google.", "org.lwjgl.", "io.netty.",
+    "it.unimi.", "org.apache.", "org.slf4j.", "oshi.", "com.llamalad7.",
+];
+
+#[derive(Debug, Default)]
+pub struct CrashSummary {
+    /// the crash report or JVM error log
+    pub report: Option<PathBuf>,
+    pub exception: Option<String>,
+    pub suspected_mods: Vec<String>,
+    pub known_issues: Vec<&'static str>,
+}
+
+/// the newest crash report or JVM error log written since `since`
+pub fn find_report(game_dir: &Path, since: SystemTime) -> Option<PathBuf> {
+    let crash_reports = fs::read_dir(game_dir.join("crash-reports")).into_iter().flatten();
+    let hs_errs = fs::read_dir(game_dir).into_iter().flatten().filter(|entry| {
+        let name = entry.as_ref().map(|e| e.file_name().to_string_lossy().to_string()).unwrap_or_default();
+        name.starts_with("hs_err_pid") && name.ends_with(".log")
+    });
+
+    crash_reports
+        .chain(hs_errs)
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .filter(|(modified, path)| *modified >= since && path.is_file())
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(_, path)| path)
+}
+
+/// the thrown exception of a crash report, or the error of a JVM error log
+fn find_exception(text: &str) -> Option<String> {
+    if text.contains("A fatal error has been detected by the Java Runtime Environment") {
+        let error = text.lines().find(|line| line.contains(" at pc=")).map(|line| line.trim_start_matches('#').trim().to_owned());
+        let frame = text.lines().skip_while(|line| !line.contains("Problematic frame:")).nth(1).map(|line| line.trim_start_matches('#').trim().to_owned());
+        return match (error, frame) {
+            (Some(error), Some(frame)) => Some(format!("{} in {}", error, frame)),
+            (error, frame) => error.or(frame),
+        };
+    }
+
+    let exception = Regex::new(r"^(Caused by: )?[\w$]+(\.[\w$]+)+(Exception|Error|Throwable)\b.*$").unwrap();
+    let mut lines = text.lines().filter(|line| exception.is_match(line));
+    let first = lines.next()?.to_owned();
+    // the root cause is the most useful part of a chain of exceptions
+    Some(match lines.rfind(|line| line.starts_with("Caused by: ")) {
+        Some(cause) => format!("{} ({})", first, cause),
+        None => first,
+    })
+}
+
+/// the mods from Fabric's (or Forge's) "Suspected Mods" section, falling back to the packages in the stack trace
+fn find_suspected_mods(text: &str) -> Vec<String> {
+    let mut lines = text.lines().skip_while(|line| !line.trim_start().starts_with("Suspected Mods:"));
+    if let Some(header) = lines.next() {
+        let inline = header.trim_start().trim_start_matches("Suspected Mods:").trim();
+        if inline.eq_ignore_ascii_case("none") || inline.eq_ignore_ascii_case("unknown") {
+            return vec![];
+        }
+        if !inline.is_empty() {
+            return vec![inline.to_owned()];
+        }
+        let mods = lines
+            .take_while(|line| line.starts_with('\t') || line.starts_with("    "))
+            .filter(|line| !line.starts_with("\t\t") && !line.starts_with("        "))
+            .map(|line| line.trim().to_owned())
+            .collect::<Vec<_>>();
+        if !mods.is_empty() {
+            return mods;
+        }
+    }
+
+    let frame = Regex::new(r"^\s*at (?:[\w.-]+//)?([\w$]+(?:\.[\w$]+)+)\.[\w$<>]+\(").unwrap();
+    // mixin handlers are named like `handler$zza000$modid$method`
+    let mixin = Regex::new(r"\$[a-z0-9]+\$([a-z][a-z0-9_]+)\$").unwrap();
+    let mut suspects: Vec<String> = vec![];
+    for line in text.lines() {
+        let Some(captures) = frame.captures(line) else { continue };
+        if let Some(mixin) = mixin.captures(line) && !suspects.contains(&mixin[1].to_owned()) {
+            suspects.push(mixin[1].to_owned());
+        }
+        let class = &captures[1];
+        if IGNORED_PACKAGES.iter().any(|ignored| class.starts_with(ignored)) {
+            continue;
+        }
+        let package = class.rsplit_once('.').map_or(class, |(package, _)| package).split('.').take(3).collect::<Vec<_>>().join(".");
+        if !suspects.contains(&package) {
+            suspects.push(package);
+        }
+    }
+    suspects.truncate(3);
+    suspects
+}
+
+pub fn analyze(text: &str) -> CrashSummary {
+    let known_issues = KNOWN_ISSUES
+        .iter()
+        .filter(|(pattern, _)| Regex::new(pattern).unwrap().is_match(text))
+        .map(|(_, issue)| *issue)
+        .collect();
+
+    CrashSummary {
+        report: None,
+        exception: find_exception(text),
+        suspected_mods: find_suspected_mods(text),
+        known_issues,
+    }
+}
+
+/// looks for a crash after the game exited, analysing the crash report or, without one, the end of the output
+pub fn check(game_dir: &Path, started: SystemTime, success: bool, recent_output: &[String]) -> Option<CrashSummary> {
+    let report = find_report(game_dir, started);
+    if success && report.is_none() {
+        return None;
+    }
+
+    let mut summary = match &report {
+        Some(path) => analyze(&String::from_utf8_lossy(&fs::read(path).unwrap_or_default())),
+        None => analyze(&recent_output.join("\n")),
+    };
+    summary.report = report;
+    Some(summary)
+}
+
+impl CrashSummary {
+    pub fn print(&self) {
+        eprintln!("The game crashed!");
+        if let Some(exception) = &self.exception {
+            eprintln!("  Exception:      {}", exception);
+        }
+        if !self.suspected_mods.is_empty() {
+            eprintln!("  Suspected mods: {}", self.suspected_mods.join(", "));
+        }
+        for issue in &self.known_issues {
+            eprintln!("  Known issue:    {}", issue);
+        }
+        match &self.report {
+            Some(report) => eprintln!("  Full report:    {}", report.display()),
+            None => eprintln!("  No crash report was written, see the output above"),
+        }
+    }
+}
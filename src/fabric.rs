@@ -1,23 +1,21 @@
-use std::fs;
-use std::io::{BufRead as _, BufReader};
+use std::{error::Error, fs};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
 
 use directories::ProjectDirs;
-use uuid::Uuid;
+use serde_json::Value;
 
-use crate::{mem, util, vanilla, version};
-use crate::version::{FabricIntermediaryVersion, FabricLoaderJSON, FabricLoaderVersion, FabricVersion, UseQuilt};
+use crate::{app::LaunchOptions, maven::Coordinate, mem, resolve, util, vanilla};
+use crate::version::{FabricLoaderJSON, FabricLoaderVersion, FabricVersion, UseQuilt};
 
-const FABRIC_GAME_VERSIONS: &'static str = "https://meta.fabricmc.net/v2/versions/game";
-const FABRIC_LOADER_VERSIONS: &'static str = "https://meta.fabricmc.net/v2/versions/loader";
-const FABRIC_INTERMEDIARY_VERSIONS: &'static str = "https://meta.fabricmc.net/v2/versions/intermediary";
-const FABRIC_MAVEN: &'static str = "https://maven.fabricmc.net/";
+const FABRIC_GAME_VERSIONS: &str = "https://meta.fabricmc.net/v2/versions/game";
+const FABRIC_LOADER_VERSIONS: &str = "https://meta.fabricmc.net/v2/versions/loader";
+const FABRIC_INTERMEDIARY_VERSIONS: &str = "https://meta.fabricmc.net/v2/versions/intermediary";
+const FABRIC_MAVEN: &str = "https://maven.fabricmc.net/";
 
-const QUILT_GAME_VERSIONS: &'static str = "https://meta.quiltmc.org/v3/versions/game";
-const QUILT_LOADER_VERSIONS: &'static str = "https://meta.quiltmc.org/v3/versions/loader";
-const QUILT_INTERMEDIARY_VERSIONS: &'static str = "https://meta.quiltmc.org/v3/versions/intermediary";
-const QUILT_MAVEN: &'static str = "https://maven.quiltmc.org/";
+const QUILT_GAME_VERSIONS: &str = "https://meta.quiltmc.org/v3/versions/game";
+const QUILT_LOADER_VERSIONS: &str = "https://meta.quiltmc.org/v3/versions/loader";
+const QUILT_INTERMEDIARY_VERSIONS: &str = "https://meta.quiltmc.org/v3/versions/intermediary";
+const QUILT_MAVEN: &str = "https://maven.quiltmc.org/";
 
 pub fn get_ver(versions: Vec<FabricVersion>, version: String) -> FabricVersion {
     let mut found = false;
@@ -33,91 +31,83 @@ pub fn get_ver(versions: Vec<FabricVersion>, version: String) -> FabricVersion {
     ver.clone()
 }
 
-pub async fn down_intermediary(loader: &FabricLoaderVersion, version: &FabricVersion, ver: PathBuf, use_quilt: UseQuilt) {
-    let is_quilt = matches!(use_quilt, UseQuilt::Yes(_));
-    let use_release = match use_quilt {
-        UseQuilt::Yes(value) => if value { "repository/release/" } else { "repository/snapshot/" },
+/// the repository of quilt's maven the loader comes from, releases or snapshots, fabric's maven has only one
+fn repository_path(use_quilt: &UseQuilt) -> &'static str {
+    match use_quilt {
+        UseQuilt::Yes(true) => "repository/release/",
+        UseQuilt::Yes(false) => "repository/snapshot/",
         UseQuilt::No => "",
-    };
-    let intermediary_versions_text = util::download_text_no_save_async(if is_quilt { QUILT_INTERMEDIARY_VERSIONS } else { FABRIC_INTERMEDIARY_VERSIONS }, "Downloaded intermediary version JSON".to_string()).await.expect("Failed to download intermediary version JSON");
-    let intermediaries: Vec<FabricIntermediaryVersion> = serde_json::from_str(intermediary_versions_text.as_str()).expect("Failed to deserialize intermediary version JSON");
-    let mut intermediary: &FabricIntermediaryVersion = &FabricIntermediaryVersion {
-        maven: "".to_string(),
-        version: "".to_string(),
-    };
-    intermediaries.iter().any(|i| {
-        if i.version == version.version {
-            intermediary = i;
-            return true;
-        }
-        return false;
-    });
-
-    let maven_path = version::maven_to_path(intermediary.maven.clone());
-    let maven_path_with_domain = format!("{}{}", FABRIC_MAVEN.to_string(), maven_path);
-    dbg!(&maven_path_with_domain);
-    let _ = util::download_async(maven_path_with_domain.as_str(), ver.join("inter.jar").as_ref(), "Downloaded intermediary...".to_string()).await.expect("Failed to download intermediary");
+    }
 }
 
-/// returns the full fabric loader JSON
-/// {loader} the loader version
-/// {version} the minecarft version
-/// {ver} the version dir
-pub async fn down(loader: &FabricLoaderVersion, version: &FabricVersion, ver: PathBuf, use_quilt: UseQuilt) -> FabricLoaderJSON {
-    let is_quilt = matches!(use_quilt, UseQuilt::Yes(_));
-    let use_release = match use_quilt {
-        UseQuilt::Yes(value) => if value { "repository/release/" } else { "repository/snapshot/" },
-        UseQuilt::No => "",
-    };
-
-    down_intermediary(loader, version, ver.clone(), use_quilt).await;
-    let loader_jar_url = format!("{}{}{}", if is_quilt { QUILT_MAVEN } else { FABRIC_MAVEN }, use_release, loader.jar_path(is_quilt));
-
-    let jar_path = ver.join("fabric.jar");
-
-    if !jar_path.exists() {
-        let _ = util::download_async(&loader_jar_url, jar_path.as_path(), "Downloaded fabric loader jar".to_string()).await.expect("Failed to download fabric JAR");
+/// points the libraries of a quilt profile JSON, which name the release repository, at the one picked with --use-release
+fn use_repository(profile_json: &str, use_quilt: &UseQuilt) -> String {
+    let UseQuilt::Yes(false) = use_quilt else { return profile_json.to_owned() };
+    let mut json: Value = serde_json::from_str(profile_json).expect("Failed to parse loader profile JSON");
+    if let Some(libraries) = json.get_mut("libraries").and_then(Value::as_array_mut) {
+        for library in libraries {
+            if library.get("url").and_then(Value::as_str).is_some_and(|url| url.starts_with(QUILT_MAVEN)) {
+                library["url"] = Value::String(format!("{}{}", QUILT_MAVEN, repository_path(use_quilt)));
+            }
+        }
     }
+    json.to_string()
+}
 
-    let loader_json_url = format!("{}{}{}", if is_quilt { QUILT_MAVEN } else { FABRIC_MAVEN }, use_release, loader.json_path(is_quilt));
-
-
-    let json_path = ver.join("fabric.json");
+/// the url of the loader's profile JSON, a version JSON inheriting from vanilla
+pub fn profile_json_url(loader: &FabricLoaderVersion, version: &FabricVersion, is_quilt: bool) -> String {
+    format!("{}/{}/{}/profile/json", if is_quilt { QUILT_LOADER_VERSIONS } else { FABRIC_LOADER_VERSIONS }, version.version, loader.version)
+}
 
-    let loader_json = if !json_path.exists() {
-        util::download_text_async(&loader_json_url, json_path.as_path(), "Downloaded fabric loader JSON".to_string()).await.expect("Failed to download fabric loader JSON")
+pub async fn get_loader(opt_loader_version: Option<String>, is_quilt: bool) -> FabricLoaderVersion {
+    let loader_versions_json = util::download_text_no_save_async(if is_quilt { QUILT_LOADER_VERSIONS } else { FABRIC_LOADER_VERSIONS }, "".to_string()).await.expect("Failed to download loader versions JSON");
+    let loader_versions: Vec<FabricLoaderVersion> = serde_json::from_str(&loader_versions_json).expect("Failed to parse fabric loader versions JSON");
+    if let Some(ver_str) = opt_loader_version {
+        loader_versions
+            .into_iter()
+            .find(|v| v.version == ver_str)
+            .expect("Loader version not found")
     } else {
-        fs::read_to_string(json_path.as_path()).unwrap()
-    };
-
-    let parsed_json: FabricLoaderJSON = serde_json::from_str(&loader_json).expect("Failed to parse loader JSON");
-
-    for lib in &parsed_json.libraries.common {
-        let path_from_maven = version::maven_to_path(lib.name.clone());
-        let path = format!("{}{}", lib.url, path_from_maven);
-        let lib_path = ver.join("libs").join(path_from_maven.clone());
-        let _ = fs::create_dir_all(lib_path.clone().parent().unwrap());
-        let _ = util::download_async(path.as_str(), &lib_path, "Downloaded common lib jar".to_owned()).await.expect("Failed to download server lib jar");
+        loader_versions.into_iter().next().expect("No loader versions found")
     }
-    println!("Downloaded common libs...");
-    for lib in &parsed_json.libraries.server {
-        let path_from_maven = version::maven_to_path(lib.name.clone());
-        let path = format!("{}{}", lib.url, path_from_maven);
-        let lib_path = ver.join("libs").join(path_from_maven.clone());
-        let _ = fs::create_dir_all(lib_path.clone().parent().unwrap());
-        let _ = util::download_async(path.as_str(), &lib_path, "Downloaded server lib jar".to_owned()).await.expect("Failed ot download server lib jar");
+}
+
+/// downloads the loader, intermediary and the common and server libraries of a dedicated server into `server_dir`
+/// returns the server main class and the classpath, relative to `server_dir`
+pub async fn install_server(version: &str, opt_loader_version: Option<String>, server_dir: &Path, use_quilt: UseQuilt) -> Result<(String, Vec<String>), Box<dyn Error>> {
+    let is_quilt = matches!(use_quilt, UseQuilt::Yes(_));
+    let use_release = repository_path(&use_quilt);
+    let maven = if is_quilt { QUILT_MAVEN } else { FABRIC_MAVEN };
+    let loader = get_loader(opt_loader_version, is_quilt).await;
+
+    let loader_json_url = format!("{}{}{}", maven, use_release, loader.json_path(is_quilt));
+    let loader_json = util::download_text_no_save_async(&loader_json_url, "Downloaded loader JSON".to_owned()).await?;
+    let parsed_json: FabricLoaderJSON = serde_json::from_str(&loader_json)?;
+
+    let mut downloads = vec![
+        (format!("{}{}{}", maven, use_release, loader.jar_path(is_quilt)), loader.jar_path(is_quilt)),
+    ];
+    // quilt servers run on fabric's intermediary as well
+    let intermediary: Coordinate = format!("net.fabricmc:intermediary:{}", version).parse()?;
+    downloads.push((intermediary.url(FABRIC_MAVEN).await?, intermediary.path()));
+
+    for lib in parsed_json.libraries.common.iter().chain(parsed_json.libraries.server.iter()) {
+        let coordinate: Coordinate = lib.name.parse()?;
+        downloads.push((coordinate.url(&lib.url).await?, coordinate.path()));
     }
-    println!("Downloaded server libs...");
-    for lib in &parsed_json.libraries.client {
-        let path_from_maven = version::maven_to_path(lib.name.clone());
-        let path = format!("{}{}", lib.url, path_from_maven);
-        let lib_path = ver.join("libs").join(path_from_maven.clone());
-        let _ = fs::create_dir_all(lib_path.clone().parent().unwrap());
-        let _ = util::download_async(path.as_str(), &lib_path, "Downloaded client lib jar".to_owned()).await.expect("Failed to download client lib jar");
+
+    let mut classpath = vec![];
+    for (url, path) in downloads {
+        let rel_path = format!("libraries/{}", path);
+        let lib_path = server_dir.join(&rel_path);
+        let _ = fs::create_dir_all(lib_path.parent().unwrap());
+        if !lib_path.exists() {
+            util::download_async(&url, &lib_path, "Downloaded server lib jar".to_owned()).await?;
+        }
+        classpath.push(rel_path);
     }
-    println!("Downloaded client libs...");
 
-    parsed_json
+    Ok((parsed_json.mainClass.server, classpath))
 }
 
 pub fn create_dirs(vers: PathBuf, ver: PathBuf) {
@@ -128,89 +118,17 @@ pub fn create_dirs(vers: PathBuf, ver: PathBuf) {
     let _ = fs::create_dir(vers.parent().unwrap().join("assets"));
 }
 
-pub fn launch(ver_dir: PathBuf, main_class: String) {
-    println!("Launching minecraft client...");
-    let main_class = dbg!(main_class);
-    let game_dir = ver_dir.join("game");
-    let _ = fs::create_dir_all(&game_dir);
-
-    let mut classpath: String = "".to_owned();
-    let libs = util::list_files_recursively(&ver_dir.join("libs"));
-    let sep = if cfg!(target_os = "windows") { ';' } else { ':' };
-
-    for lib in libs {
-        classpath.push_str(lib.to_str().unwrap());
-        classpath.push(sep);
-    }
-
-    classpath.push_str(ver_dir.join("inter.jar").to_str().unwrap());
-    classpath.push(sep);
-    classpath.push_str(ver_dir.join("client.jar").to_str().unwrap());
-    classpath.push(sep);
-    classpath.push_str(ver_dir.join("fabric.jar").to_str().unwrap());
-
-    let mut cmd: Vec<String> = vec![];
-    if cfg!(target_os = "macos") {
-        cmd.push("-XstartOnFirstThread".to_owned());
-    }
-    cmd.push("-cp".to_owned());
-    cmd.push(classpath);
-    cmd.push(main_class);
-    cmd.push("--gameDir".to_string());
-    let game_dir = dbg!(ver_dir.parent().unwrap().parent().unwrap().join("game").to_str().unwrap().to_owned());
-    cmd.push(game_dir.clone());
-
-    cmd.push("--assetsDir".to_string());
-    let assets_dir = dbg!(ver_dir.parent().unwrap().parent().unwrap().join("assets").to_str().unwrap().to_string());
-    cmd.push(assets_dir);
-    cmd.push("--assetIndex".to_string());
-    let ver = ver_dir.file_name().unwrap().to_str().unwrap().replace("fabric-", "").replace("quilt-", "");
-    cmd.push(ver);
-    cmd.push("--uuid".to_string());
-    cmd.push(Uuid::new_v4().to_string());
-
-    let mut process = Command::new("java")
-        .current_dir(game_dir)
-        .args(&cmd)
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("Failed to run Minecraft");
-
-    let stdout = process.stdout.take().expect("Failed to take stdout");
-    let reader = BufReader::new(stdout);
-    for line in reader.lines() {
-        let line = line.expect("Failed to read stdout line");
-        println!("{}", line);
-    }
-
-    let status = process.wait().expect("Failed to wait for child");
-    println!("Exited with {}", status);
-}
-
-pub async fn handle(opt_version: Option<String>, opt_loader_version: Option<String>, limit: String, use_quilt: UseQuilt) {
+pub async fn handle(opt_version: Option<String>, opt_loader_version: Option<String>, limit: String, use_quilt: UseQuilt, options: LaunchOptions) {
     let is_quilt = matches!(use_quilt, UseQuilt::Yes(_));
-    let use_release = match use_quilt {
-        UseQuilt::Yes(value) => if value { "repository/release/" } else { "repository/snapshot/" },
-        UseQuilt::No => "",
-    };
     let game_versions = util::download_text_no_save_async(if is_quilt { QUILT_GAME_VERSIONS } else { FABRIC_GAME_VERSIONS }, "Downloaded fabric game versions json".to_string()).await.expect("Failed to download fabric game versions json");
     let versions: Vec<FabricVersion> = serde_json::from_str(game_versions.as_str()).expect("Failed to parse fabric game versions JSON");
-    let ver = if opt_version.is_some() {
-        get_ver(versions, opt_version.unwrap())
+    let ver = if let Some(opt_version) = opt_version {
+        get_ver(versions, opt_version)
     } else {
         versions.first().unwrap().clone()
     };
 
-    let loader_versions_json = util::download_text_no_save_async(if is_quilt { QUILT_LOADER_VERSIONS } else { FABRIC_LOADER_VERSIONS }, "".to_string()).await.expect("Failed to download loader versions JSON");
-    let loader_versions: Vec<FabricLoaderVersion> = serde_json::from_str(&loader_versions_json).expect("Failed to parse fabric loader versions JSON");
-    let loader = if let Some(ver_str) = opt_loader_version {
-        &loader_versions
-            .into_iter()
-            .find(|v| v.version == ver_str)
-            .expect("Loader version not found")
-    } else {
-        loader_versions.first().expect("No loader versions found")
-    };
+    let loader = &get_loader(opt_loader_version, is_quilt).await;
 
     let (loader_version, loader_build) = (loader.version.as_str(), loader.build);
 
@@ -231,42 +149,16 @@ pub async fn handle(opt_version: Option<String>, opt_loader_version: Option<Stri
     let vers = data_dir.join("vers");
     let ver_path = vers.join(format!("{}-{}", if is_quilt { "quilt" } else { "fabric" }, ver.version.clone()));
 
-    create_dirs(vers, ver_path.clone());
-
-    vanilla::handle(Some(ver.version.clone()), limit.clone(), false, Some(ver_path.as_path())).await;
-    let parsed_json = down(loader, &ver, ver_path.clone(), use_quilt).await;
-
-    let _ = fs::remove_dir_all(ver_path.join("libs").join("META-INF"));
-
-    let asm = ver_path.join("libs").join("org").join("ow2").join("asm").join("asm");
-    let entries = fs::read_dir(&asm).expect("Failed to read lib dir").filter_map(|e| e.ok()).filter(|e| e.file_type().map(|ft| ft.is_dir()).unwrap_or(false));
+    create_dirs(vers.clone(), ver_path.clone());
 
-
-    let mut min_dir: Option<(f64, String)> = None;
-    let mut len = 0;
-    for entry in entries {
-        len += 1;
-        let name = entry.file_name().into_string().unwrap_or_default();
-        if let Ok(num) = name.parse::<f64>() {
-            match min_dir {
-                Some((min_val, _)) if num < min_val => {
-                    min_dir = Some((num, name));
-                }
-                None => {
-                    min_dir = Some((num, name));
-                }
-                _ => {}
-            }
-        }
-    }
-
-    if let Some((_, dir_name)) = min_dir {
-        if len > 1 {
-            let _ = fs::remove_dir_all(asm.join(Path::new(&dir_name)));
-        }
-    } else {
-        println!("No numeric-named directories found.");
+    let profile_json = util::download_text_no_save_async(&profile_json_url(loader, &ver, is_quilt), "Downloaded loader profile JSON".to_owned()).await.expect("Failed to download loader profile JSON");
+    let profile_json = use_repository(&profile_json, &use_quilt);
+    if let Err(err) = resolve::install(&ver_path, &profile_json, limit.clone()).await {
+        eprintln!("FATAL: Failed to install {}:\n{}", ver_path.file_name().unwrap().to_string_lossy(), err);
+        std::process::exit(-1);
     }
 
-    launch(ver_path, parsed_json.mainClass.client);
+    let resolved = resolve::resolve(&ver_path).expect("Failed to resolve loader version");
+    vanilla::download_logging_config(&resolved.json, &resolved.data_dir().join("assets")).await;
+    vanilla::launch(resolved, limit, options);
 }
@@ -0,0 +1,183 @@
+use std::{collections::HashSet, fs, path::{Path, PathBuf}};
+
+use cli_table::{Cell as _, Table as _};
+use directories::ProjectDirs;
+use indicatif::HumanBytes;
+use serde_json::Value;
+
+use crate::{assets, natives, overrides, resolve, rules, store, version::Library};
+
+/// Unreferenced files and dirs of one kind
+struct Garbage {
+    kind: &'static str,
+    paths: Vec<PathBuf>,
+}
+
+/// the size of a file, or of everything in a dir
+fn size_of(path: &Path) -> u64 {
+    if path.is_dir() {
+        walk(path).iter().map(|file| size_of(file)).sum()
+    } else {
+        fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0)
+    }
+}
+
+/// the files in a dir and its subdirs
+fn walk(dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for path in entries(dir) {
+        if path.is_dir() {
+            files.extend(walk(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+fn entries(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir).into_iter().flatten().filter_map(|e| e.ok()).map(|e| e.path()).collect()
+}
+
+fn remove_empty_dirs(dir: &Path) {
+    for path in entries(dir) {
+        if path.is_dir() {
+            remove_empty_dirs(&path);
+            // only succeeds when it's empty
+            let _ = fs::remove_dir(&path);
+        }
+    }
+}
+
+/// the paths, relative to its libs dir, of the libraries a version's own JSON puts there,
+/// and where they are in the store
+fn library_paths(ver_dir: &Path) -> Result<(HashSet<PathBuf>, HashSet<PathBuf>), String> {
+    let json = resolve::read_json(ver_dir).map_err(|err| err.to_string())?;
+    let libraries: Vec<Library> = serde_json::from_value(json.get("libraries").cloned().unwrap_or(Value::Array(vec![]))).map_err(|err| err.to_string())?;
+
+    let mut paths = HashSet::new();
+    let mut stored = HashSet::new();
+    for library in overrides::apply(&libraries).iter().filter(|library| rules::library_allowed(library)) {
+        for file in library.files()? {
+            stored.insert(store::stored_path(&file.path, file.sha1.as_deref()));
+            paths.insert(PathBuf::from(file.path));
+        }
+    }
+    Ok((paths, stored))
+}
+
+/// deletes the libraries, assets and natives no installed version uses
+pub fn run(dry_run: bool) {
+    let proj_dirs = ProjectDirs::from("me", "illia", "mc_cli").unwrap();
+    let data_dir = proj_dirs.data_dir();
+    let assets_dir = data_dir.join("assets");
+
+    let mut stale_libs = Garbage { kind: "Version libs", paths: vec![] };
+    let mut used_libs = HashSet::new();
+    let mut used_indexes = HashSet::new();
+    let mut used_log_configs = HashSet::new();
+    let mut used_natives = HashSet::new();
+
+    let installed = entries(&data_dir.join("vers")).into_iter().filter(|dir| dir.join("version.json").is_file());
+    for ver_dir in installed {
+        // anything missed here would get deleted, so a version that can't be read stops it
+        let unreadable = |err: String| -> ! {
+            eprintln!("FATAL: Can't tell what {} uses ({}), fix or remove it first.", ver_dir.display(), err);
+            std::process::exit(-1);
+        };
+        let (paths, stored) = library_paths(&ver_dir).unwrap_or_else(|err| unreadable(err));
+        let resolved = resolve::resolve(&ver_dir).unwrap_or_else(|err| unreadable(err.to_string()));
+
+        let libs = ver_dir.join("libs");
+        // natives extracted into libs by older versions of mc_cli end up here too
+        stale_libs.paths.extend(walk(&libs).into_iter().filter(|file| !paths.contains(file.strip_prefix(&libs).unwrap())));
+        used_libs.extend(stored);
+        used_indexes.insert(resolved.json.assetIndex.id.clone());
+        if let Some(logging) = &resolved.json.logging.client {
+            used_log_configs.insert(logging.file.id.clone());
+        }
+        used_natives.insert(natives::natives_dir(&resolved));
+    }
+
+    let store_dir = store::store_dir();
+    let stored_libs = Garbage {
+        kind: "Libraries",
+        paths: walk(&store_dir).into_iter().filter(|file| !used_libs.contains(file)).collect(),
+    };
+
+    let is_used_index = |path: &Path| path.file_stem().is_some_and(|stem| used_indexes.contains(&*stem.to_string_lossy()));
+    let indexes = Garbage {
+        kind: "Asset indexes",
+        paths: entries(&assets_dir.join("indexes")).into_iter().filter(|path| !is_used_index(path)).collect(),
+    };
+    let virtual_assets = Garbage {
+        kind: "Legacy assets",
+        paths: entries(&assets_dir.join("virtual")).into_iter().filter(|path| !path.file_name().is_some_and(|name| used_indexes.contains(&*name.to_string_lossy()))).collect(),
+    };
+
+    let mut used_objects = HashSet::new();
+    let mut missing_index = false;
+    for id in &used_indexes {
+        match assets::read_index(&assets_dir, id) {
+            Some(index) => used_objects.extend(index.objects.into_values().map(|object| object.hash)),
+            None => missing_index = true,
+        }
+    }
+    let objects = Garbage {
+        kind: "Asset objects",
+        // which objects a version uses can't be told without its index
+        paths: if missing_index {
+            eprintln!("Warning: Some asset indexes are missing, keeping every asset object");
+            vec![]
+        } else {
+            walk(&assets_dir.join("objects"))
+                .into_iter()
+                .filter(|path| !path.file_name().is_some_and(|name| used_objects.contains(&*name.to_string_lossy())))
+                .collect()
+        },
+    };
+
+    let log_configs = Garbage {
+        kind: "Log configs",
+        paths: entries(&assets_dir.join("log_configs")).into_iter().filter(|path| !path.file_name().is_some_and(|name| used_log_configs.contains(&*name.to_string_lossy()))).collect(),
+    };
+    let natives = Garbage {
+        kind: "Natives",
+        paths: entries(&data_dir.join("natives")).into_iter().filter(|path| !used_natives.contains(path)).collect(),
+    };
+
+    let mut rows = vec![
+        vec![
+            "KIND".cell(),
+            "UNUSED".cell(),
+            "SIZE".cell(),
+        ]
+    ];
+    let mut total = 0;
+    for garbage in [stored_libs, stale_libs, indexes, objects, virtual_assets, log_configs, natives] {
+        let size = garbage.paths.iter().map(|path| size_of(path)).sum::<u64>();
+        total += size;
+        rows.push(vec![garbage.kind.cell(), garbage.paths.len().cell(), HumanBytes(size).cell()]);
+
+        if !dry_run {
+            for path in &garbage.paths {
+                let result = if path.is_dir() { fs::remove_dir_all(path) } else { fs::remove_file(path) };
+                if let Err(err) = result {
+                    eprintln!("Warning: Failed to delete {}: {}", path.display(), err);
+                }
+            }
+        }
+    }
+
+    let table = rows.table();
+    println!("{}", table.display().unwrap());
+
+    if dry_run {
+        println!("Would reclaim {}, run without --dry-run to delete it", HumanBytes(total));
+    } else {
+        for dir in [data_dir.join("vers"), store_dir, assets_dir.join("objects"), assets_dir.join("virtual")] {
+            remove_empty_dirs(&dir);
+        }
+        println!("Reclaimed {}", HumanBytes(total));
+    }
+}
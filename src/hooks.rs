@@ -0,0 +1,50 @@
+use std::{path::Path, process::Command};
+
+/// What the hooks get told about the launch, through `MC_CLI_*` environment variables
+pub struct HookContext<'a> {
+    pub instance: &'a str,
+    pub version: &'a str,
+    pub game_dir: &'a Path,
+    pub log: &'a Path,
+    /// only set for post-exit hooks
+    pub exit_code: Option<i32>,
+    pub crash_report: Option<&'a Path>,
+}
+
+impl HookContext<'_> {
+    fn env(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("MC_CLI_INSTANCE", self.instance.to_owned()),
+            ("MC_CLI_VERSION", self.version.to_owned()),
+            ("MC_CLI_GAME_DIR", self.game_dir.to_string_lossy().to_string()),
+            ("MC_CLI_LOG", self.log.to_string_lossy().to_string()),
+            ("MC_CLI_EXIT_CODE", self.exit_code.map(|code| code.to_string()).unwrap_or_default()),
+            ("MC_CLI_CRASH_REPORT", self.crash_report.map(|path| path.to_string_lossy().to_string()).unwrap_or_default()),
+        ]
+    }
+}
+
+/// runs the hooks one by one in the game dir, stopping at the first one that fails
+pub fn run(hooks: &[String], context: &HookContext) -> Result<(), String> {
+    for hook in hooks {
+        println!("Running hook: {}", hook);
+        let mut command = if cfg!(target_os = "windows") {
+            let mut command = Command::new("cmd");
+            command.args(["/C", hook]);
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.args(["-c", hook]);
+            command
+        };
+        let status = command
+            .current_dir(context.game_dir)
+            .envs(context.env())
+            .status()
+            .map_err(|err| format!("Failed to run hook `{}`: {}", hook, err))?;
+        if !status.success() {
+            return Err(format!("Hook `{}` failed with {}", hook, status));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}};
+
+use cli_table::{Cell as _, Table as _};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::app::LaunchOptions;
+
+const CONFIG_FILE: &str = "instance.json";
+
+/// Launch defaults of an installed version, saved as instance.json in its version dir.
+/// The flags passed on the command line win over these.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct InstanceConfig {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fullscreen: Option<bool>,
+    pub demo: Option<bool>,
+    /// commands the JVM gets run through, like `gamemoderun` or `mangohud --dlsym`, outermost first
+    pub wrappers: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    /// shell commands run before the game starts, a failing one cancels the launch
+    pub pre_launch: Vec<String>,
+    /// shell commands run after the game exits
+    pub post_exit: Vec<String>,
+}
+
+impl InstanceConfig {
+    /// fills the options which weren't given on the command line
+    pub fn apply(&self, mut options: LaunchOptions) -> LaunchOptions {
+        options.width = options.width.or(self.width);
+        options.height = options.height.or(self.height);
+        options.fullscreen = flag(options.fullscreen, options.no_fullscreen, self.fullscreen);
+        options.demo = flag(options.demo, options.no_demo, self.demo);
+        // the instance's wrappers go around the ones given for this launch
+        options.wrappers = self.wrappers.iter().cloned().chain(options.wrappers).collect();
+        let mut env = self.env.iter().map(|(key, value)| (key.clone(), value.clone())).collect::<Vec<_>>();
+        env.extend(options.env);
+        options.env = env;
+        options.pre_launch = self.pre_launch.iter().cloned().chain(options.pre_launch).collect();
+        options.post_exit = self.post_exit.iter().cloned().chain(options.post_exit).collect();
+        options
+    }
+}
+
+/// a flag given on the command line as `--<flag>` or `--no-<flag>`, or the instance's default
+fn flag(on: bool, off: bool, default: Option<bool>) -> bool {
+    match (on, off) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => default.unwrap_or(false),
+    }
+}
+
+pub fn instance_dir(name: &str) -> PathBuf {
+    let proj_dirs = ProjectDirs::from("me", "illia", "mc_cli").unwrap();
+    let dir = proj_dirs.data_dir().join("vers").join(name);
+    if !dir.join("version.json").is_file() {
+        eprintln!("FATAL: There's no installed version called {}.", name);
+        std::process::exit(-1);
+    }
+    dir
+}
+
+/// the config of the version in `ver_dir`, or the defaults if it has none
+pub fn load(ver_dir: &Path) -> InstanceConfig {
+    match fs::read_to_string(ver_dir.join(CONFIG_FILE)) {
+        Ok(text) => serde_json::from_str(&text).unwrap_or_else(|err| {
+            eprintln!("Warning: Ignoring invalid {}: {}", ver_dir.join(CONFIG_FILE).display(), err);
+            InstanceConfig::default()
+        }),
+        Err(_) => InstanceConfig::default(),
+    }
+}
+
+fn to_map(config: &InstanceConfig) -> serde_json::Map<String, Value> {
+    match serde_json::to_value(config).unwrap() {
+        Value::Object(map) => map,
+        _ => unreachable!(),
+    }
+}
+
+pub fn get(name: String, key: String) {
+    let config = load(&instance_dir(&name));
+    match to_map(&config).get(&key) {
+        Some(value) => println!("{}", value),
+        None => {
+            eprintln!("FATAL: {} isn't an instance setting.", key);
+            std::process::exit(-1);
+        },
+    }
+}
+
+/// sets a key, the value is read as JSON (numbers, booleans, lists...) and as a plain string otherwise
+pub fn set(name: String, key: String, value: String) {
+    let dir = instance_dir(&name);
+    let mut map = to_map(&load(&dir));
+    if !map.contains_key(&key) {
+        eprintln!("FATAL: {} isn't an instance setting, the settings are: {}", key, map.keys().cloned().collect::<Vec<_>>().join(", "));
+        std::process::exit(-1);
+    }
+
+    let value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+    map.insert(key.clone(), value);
+    let config: InstanceConfig = match serde_json::from_value(Value::Object(map)) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("FATAL: Invalid value for {}: {}", key, err);
+            std::process::exit(-1);
+        },
+    };
+
+    fs::write(dir.join(CONFIG_FILE), serde_json::to_string_pretty(&config).unwrap()).expect("Failed to write instance config");
+}
+
+pub fn list(name: String) {
+    let config = load(&instance_dir(&name));
+
+    let mut rows = vec![
+        vec![
+            "KEY".cell(),
+            "VALUE".cell(),
+        ]
+    ];
+
+    for (key, value) in to_map(&config) {
+        rows.push(vec![key.cell(), value.to_string().cell()]);
+    }
+
+    let table = rows.table();
+    println!("{}", table.display().unwrap());
+}
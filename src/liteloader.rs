@@ -2,23 +2,32 @@ use std::{fs, path::PathBuf};
 
 use directories::ProjectDirs;
 
-use crate::{util, vanilla, version::{LiteLoaderVersions, MavenMetadataRoot}};
+use serde_json::{json, Value};
 
-const LITELOADER_VERSIONS_JSON: &'static str = "https://dl.liteloader.com/versions/versions.json";
+use crate::{app::LaunchOptions, maven::Coordinate, resolve, util, vanilla, version::{LiteLoaderLibrary, LiteLoaderVersions}};
 
-pub async fn handle(opt_version: Option<String>, opt_loader_version: Option<String>, limit: String) {
+const LITELOADER_VERSIONS_JSON: &str = "https://dl.liteloader.com/versions/versions.json";
+
+fn liteloader_coordinate(version: &str) -> Coordinate {
+    format!("com.mumfrey:liteloader:{}", version).parse().unwrap_or_else(|err| {
+        eprintln!("FATAL: {}", err);
+        std::process::exit(-1);
+    })
+}
+
+pub async fn handle(opt_version: Option<String>, opt_loader_version: Option<String>, limit: String, options: LaunchOptions) {
     let versions_json_text = util::download_text_no_save_async(LITELOADER_VERSIONS_JSON, "Downloaded liteloader versions json".to_owned()).await.expect("Failed to download liteloader versions json");
     tokio::fs::write("ver.json", versions_json_text.to_string()).await.unwrap();
     let versions_json: LiteLoaderVersions = serde_json::from_str(&versions_json_text).expect("Failed to parse liteloader versions");
     let meta = versions_json.meta;
     let versions = versions_json.versions;
-    let mut version = String::new();
-    if opt_version.is_some() {
-        if !versions.iter().any(|i| *i.0 == opt_version.clone().unwrap()) {
+    let version;
+    if let Some(opt_version) = opt_version {
+        if !versions.iter().any(|i| *i.0 == opt_version) {
             eprintln!("The version you provided doesn't exist");
             std::process::exit(-1);
         }
-        version = opt_version.clone().unwrap();
+        version = opt_version;
     } else {
         let mut max = 0;
         for version in &versions {
@@ -33,25 +42,67 @@ pub async fn handle(opt_version: Option<String>, opt_loader_version: Option<Stri
     let data_dir = proj_dirs.data_dir();
     let vers = data_dir.join("vers");
     let ver_path = vers.join(format!("{}-{}", "liteloader", version));
-    create_dirs(vers, ver_path.clone());
+    create_dirs(vers.clone(), ver_path.clone());
+
+    vanilla::handle(Some(version.clone()), limit.clone(), false, LaunchOptions::default()).await;
+
+    let loader_key = opt_loader_version.unwrap_or("latest".to_owned());
+    let repo = &versions[&version].repo;
+    let (tweak_class, ll_version, mut libraries, ll_url) = if let Some(artefacts) = &versions[&version].artefacts {
+        let tweaks = artefacts.liteloader.get(&loader_key).expect("Loader version not found");
+        let ll_url = format!("{}com/mumfrey/liteloader/{}/{}", &repo.url, version, tweaks.file);
 
-    if let Some(lite_loader_artifact) = &versions[&version].artefacts {
-        println!("{:?}", versions[&version].artefacts);
+        (tweaks.tweakClass.clone(), tweaks.version.clone(), tweaks.libraries.clone(), ll_url)
     } else if let Some(snap) = &versions[&version].snapshots {
-        let repo = &versions[&version].repo;
-        let liteloader_path = &format!("{}com/mumfrey/liteloader/{}", &repo.url, snap.liteloader["latest"].version);
-        let metadata = util::download_text_no_save_async(&format!("{}/maven-metadata.xml", liteloader_path), "Downloaded metadata".to_owned()).await.expect("Failed to download metadata");
-        let xmled_meta: MavenMetadataRoot = serde_xml_rs::from_str(&metadata).unwrap();
+        let tweaks = snap.liteloader.get(&loader_key).expect("Loader version not found");
+        let ll_url = match liteloader_coordinate(&tweaks.version).url(&repo.url).await {
+            Ok(url) => url,
+            Err(err) => {
+                eprintln!("FATAL: Failed to find liteloader {}: {}", tweaks.version, err);
+                std::process::exit(-1);
+            },
+        };
+
+        let mut libraries = tweaks.libraries.clone();
+        libraries.extend(snap.libraries.iter().filter(|lib| !tweaks.libraries.iter().any(|l| l.name == lib.name)).cloned());
+        (tweaks.tweakClass.clone(), tweaks.version.clone(), libraries, ll_url)
+    } else {
+        eprintln!("The version you provided has no liteloader builds");
+        std::process::exit(-1);
+    };
+
+    let ll_name = format!("com.mumfrey:liteloader:{}", ll_version);
+    let ll_jar_path = ver_path.join("libs").join(liteloader_coordinate(&ll_version).path());
+    let _ = fs::create_dir_all(ll_jar_path.parent().unwrap());
+    if !ll_jar_path.exists() {
+        println!("{}", ll_url);
+        let _ = util::download_async(ll_url.as_str(), ll_jar_path.as_path(), "Downloaded ll jar".to_string()).await.expect("Failed to download ll jar");
+    }
+    libraries.insert(0, LiteLoaderLibrary { name: ll_name, url: None });
 
-        let ll_jar_path = ver_path.join("ll.jar");
-        let ll_path = format!("{}/liteloader-{}-{}-{}.jar", &liteloader_path, &version, xmled_meta.versioning.snapshot.timestamp, xmled_meta.versioning.snapshot.buildNumber);
+    // liteloader only exists for versions using the old `minecraftArguments`, which a child replaces instead of appending to
+    let parent = resolve::read_json(&vers.join(&version)).expect("Failed to read vanilla version json");
+    let minecraft_arguments = parent.get("minecraftArguments").and_then(Value::as_str).unwrap_or_default();
 
-        println!("{}", ll_path);
+    let child = json!({
+        "id": ver_path.file_name().unwrap().to_string_lossy(),
+        "inheritsFrom": version,
+        "mainClass": "net.minecraft.launchwrapper.Launch",
+        "minecraftArguments": format!("{} --tweakClass {}", minecraft_arguments, tweak_class),
+        "libraries": libraries.iter().map(|lib| match &lib.url {
+            Some(url) => json!({ "name": lib.name, "url": url }),
+            None => json!({ "name": lib.name }),
+        }).collect::<Vec<_>>(),
+    });
 
-        let ll = util::download_async(ll_path.as_str(), &ll_jar_path.as_path(), "Downloaded ll jar".to_string()).await.expect("Failed to download ll jar");
+    if let Err(err) = resolve::install(&ver_path, &child.to_string(), limit.clone()).await {
+        eprintln!("FATAL: Failed to install liteloader {}:\n{}", version, err);
+        std::process::exit(-1);
     }
 
-    vanilla::handle(Some(version), limit.clone(), false, Some(ver_path.as_path())).await;
+    let resolved = resolve::resolve(&ver_path).expect("Failed to resolve liteloader version");
+    vanilla::download_logging_config(&resolved.json, &resolved.data_dir().join("assets")).await;
+    vanilla::launch(resolved, limit, options);
 }
 
 pub fn create_dirs(vers: PathBuf, ver: PathBuf) {
@@ -0,0 +1,223 @@
+use std::{fs::{File, OpenOptions}, io::{IsTerminal as _, Write as _}, path::Path};
+
+use chrono::{Local, TimeZone as _};
+use regex::Regex;
+
+use crate::app::LogLevel;
+
+const EVENT_START: &str = "<log4j:Event";
+const EVENT_END: &str = "</log4j:Event>";
+
+/// A log event written by log4j's XMLLayout, which the game uses for stdout when it runs with Mojang's logging config
+#[derive(Debug)]
+pub struct Event {
+    pub logger: String,
+    pub timestamp: i64,
+    pub level: LogLevel,
+    pub thread: String,
+    pub message: String,
+    pub throwable: Option<String>,
+}
+
+pub enum Output {
+    Event(Event),
+    /// output which isn't part of an event, like lines printed before log4j started
+    Line(String),
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#10;", "\n")
+        .replace("&#13;", "\r")
+        .replace("&amp;", "&")
+}
+
+/// the text of `<tag>`, which is usually CDATA
+fn element_text(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", tag))?;
+    let text = &xml[start..end];
+    Some(match text.strip_prefix("<![CDATA[").and_then(|text| text.strip_suffix("]]>")) {
+        Some(cdata) => cdata.to_owned(),
+        None => unescape(text),
+    })
+}
+
+fn parse_level(level: &str) -> LogLevel {
+    match level {
+        "TRACE" => LogLevel::Trace,
+        "DEBUG" => LogLevel::Debug,
+        "WARN" => LogLevel::Warn,
+        "ERROR" => LogLevel::Error,
+        "FATAL" => LogLevel::Fatal,
+        _ => LogLevel::Info,
+    }
+}
+
+/// Collects the lines of the game's stdout into events, which can span multiple lines
+pub struct EventParser {
+    buffer: Option<String>,
+    attribute: Regex,
+}
+
+impl EventParser {
+    fn new() -> EventParser {
+        EventParser { buffer: None, attribute: Regex::new(r#"(\w+)="([^"]*)""#).unwrap() }
+    }
+
+    /// feeds a line of output, returning an event once all of its lines were read
+    pub fn push(&mut self, line: &str) -> Option<Output> {
+        match &mut self.buffer {
+            Some(buffer) => {
+                buffer.push('\n');
+                buffer.push_str(line);
+            },
+            None if line.trim_start().starts_with(EVENT_START) => self.buffer = Some(line.to_owned()),
+            None => return Some(Output::Line(line.to_owned())),
+        }
+
+        if !self.buffer.as_ref().unwrap().contains(EVENT_END) {
+            return None;
+        }
+        let xml = self.buffer.take().unwrap();
+        Some(self.parse(&xml).map(Output::Event).unwrap_or(Output::Line(xml)))
+    }
+
+    /// the lines of an event whose end never came, like when the game died while writing it
+    pub fn finish(&mut self) -> Option<String> {
+        self.buffer.take()
+    }
+
+    fn parse(&self, xml: &str) -> Option<Event> {
+        let start = xml.find(EVENT_START)?;
+        let tag = &xml[start..start + xml[start..].find('>')?];
+        let attribute = |name: &str| {
+            self.attribute
+                .captures_iter(tag)
+                .find(|captures| &captures[1] == name)
+                .map(|captures| unescape(&captures[2]))
+        };
+
+        Some(Event {
+            logger: attribute("logger").unwrap_or_default(),
+            timestamp: attribute("timestamp")?.parse().ok()?,
+            level: parse_level(&attribute("level")?),
+            thread: attribute("thread").unwrap_or_default(),
+            message: element_text(xml, "log4j:Message").unwrap_or_default(),
+            throwable: element_text(xml, "log4j:Throwable"),
+        })
+    }
+}
+
+impl Event {
+    /// formats the event like the vanilla console does: `[12:34:56] [Render thread/INFO]: message`
+    pub fn format(&self, color: bool) -> String {
+        let time = Local
+            .timestamp_millis_opt(self.timestamp)
+            .single()
+            .map(|time| time.format("%H:%M:%S").to_string())
+            .unwrap_or_default();
+        let level = format!("{:?}", self.level).to_uppercase();
+        let mut text = self.message.clone();
+        if let Some(throwable) = &self.throwable {
+            text.push('\n');
+            text.push_str(throwable.trim_end());
+        }
+
+        if !color {
+            return format!("[{}] [{}/{}]: {}", time, self.thread, level, text);
+        }
+        let level_color = match self.level {
+            LogLevel::Trace | LogLevel::Debug => "\x1b[2m",
+            LogLevel::Info => "\x1b[32m",
+            LogLevel::Warn => "\x1b[33m",
+            LogLevel::Error | LogLevel::Fatal => "\x1b[31m",
+        };
+        let text_color = match self.level {
+            LogLevel::Warn => "\x1b[33m",
+            LogLevel::Error | LogLevel::Fatal => "\x1b[31m",
+            _ => "",
+        };
+        format!("\x1b[90m[{}]\x1b[0m [{}/{}{}\x1b[0m]: {}{}\x1b[0m", time, self.thread, level_color, level, text_color, text)
+    }
+}
+
+/// Prints the game's stdout, turning log4j events into readable lines and copying them to a log file
+pub struct Printer {
+    parser: EventParser,
+    min_level: LogLevel,
+    color: bool,
+    file: Option<File>,
+}
+
+impl Printer {
+    pub fn new(min_level: LogLevel, log_file: Option<&Path>) -> Printer {
+        let file = log_file.map(|path| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .unwrap_or_else(|err| panic!("Failed to open log file {}: {}", path.display(), err))
+        });
+        Printer { parser: EventParser::new(), min_level, color: std::io::stdout().is_terminal(), file }
+    }
+
+    pub fn push(&mut self, line: &str) {
+        let (text, plain) = match self.parser.push(line) {
+            None => return,
+            Some(Output::Line(line)) => (line.clone(), line),
+            Some(Output::Event(event)) => {
+                if event.level < self.min_level {
+                    return;
+                }
+                (event.format(self.color), event.format(false))
+            },
+        };
+
+        self.print(&text, &plain);
+    }
+
+    /// prints what's left of an unfinished event as it is, it's often the last thing a crashing game said
+    pub fn finish(&mut self) {
+        if let Some(xml) = self.parser.finish() {
+            self.print(&xml, &xml);
+        }
+    }
+
+    fn print(&mut self, text: &str, plain: &str) {
+        println!("{}", text);
+        if let Some(file) = &mut self.file {
+            let _ = writeln!(file, "{}", plain);
+        }
+    }
+
+    /// stderr isn't written by log4j, so it's printed as it is
+    pub fn push_stderr(&mut self, line: &str) {
+        if self.color {
+            eprintln!("\x1b[31m{}\x1b[0m", line);
+        } else {
+            eprintln!("{}", line);
+        }
+        if let Some(file) = &mut self.file {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+}
+
+/// turns a whole log into readable lines without colours, events below `min_level` are left out
+pub fn format_plain(text: &str, min_level: LogLevel) -> Vec<String> {
+    let mut parser = EventParser::new();
+    let mut lines = vec![];
+    for line in text.lines() {
+        match parser.push(line) {
+            Some(Output::Line(line)) => lines.push(line),
+            Some(Output::Event(event)) if event.level >= min_level => lines.extend(event.format(false).lines().map(str::to_owned)),
+            _ => {},
+        }
+    }
+    lines.extend(parser.finish().iter().flat_map(|xml| xml.lines().map(str::to_owned)));
+    lines
+}
@@ -0,0 +1,184 @@
+use std::{fs::{self, File}, io::{self, Read as _, Write as _}, path::{Path, PathBuf}};
+
+use cli_table::{Cell as _, Table as _};
+use directories::ProjectDirs;
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+
+use crate::{app::LogLevel, log4j, process, running};
+
+/// how many launch logs are kept per instance, the oldest ones get deleted
+const MAX_LAUNCH_LOGS: usize = 20;
+/// the prefix of the names of the game's own logs, as opposed to launch logs named `<instance>/<timestamp>`
+const GAME_PREFIX: &str = "game/";
+
+fn data_dir() -> PathBuf {
+    let proj_dirs = ProjectDirs::from("me", "illia", "mc_cli").unwrap();
+    proj_dirs.data_dir().to_path_buf()
+}
+
+fn launches_dir() -> PathBuf {
+    data_dir().join("launches")
+}
+
+fn game_logs_dir() -> PathBuf {
+    data_dir().join("game").join("logs")
+}
+
+fn is_log(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    name.ends_with(".log") || name.ends_with(".log.gz")
+}
+
+/// the log files of a dir, oldest first
+pub fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.is_file() && is_log(path))
+        .collect::<Vec<_>>();
+    // launch logs are named by their timestamp, so this is also the order they were written in
+    files.sort();
+    files
+}
+
+/// reads a log, decompressing it if it's gzipped
+pub fn read_log(path: &Path) -> io::Result<String> {
+    let mut data = vec![];
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        GzDecoder::new(File::open(path)?).read_to_end(&mut data)?;
+    } else {
+        File::open(path)?.read_to_end(&mut data)?;
+    }
+    Ok(String::from_utf8_lossy(&data).to_string())
+}
+
+fn compress(path: &Path) -> io::Result<()> {
+    let mut gz_name = path.file_name().unwrap().to_os_string();
+    gz_name.push(".gz");
+    let mut encoder = GzEncoder::new(File::create(path.with_file_name(gz_name))?, Compression::default());
+    encoder.write_all(&fs::read(path)?)?;
+    encoder.finish()?;
+    fs::remove_file(path)
+}
+
+/// gzips the finished launch logs in `dir` and deletes the oldest ones past the limit
+pub fn rotate(dir: &Path) {
+    let in_use = running::states().into_iter().filter(|state| process::is_alive(state.pid)).map(|state| state.log).collect::<Vec<_>>();
+
+    for path in log_files(dir) {
+        if path.extension().is_some_and(|ext| ext == "log") && !in_use.contains(&path)
+            && let Err(err) = compress(&path) {
+            eprintln!("Warning: Failed to compress {}: {}", path.display(), err);
+        }
+    }
+
+    let files = log_files(dir);
+    for path in files.iter().take(files.len().saturating_sub(MAX_LAUNCH_LOGS)) {
+        if !in_use.contains(path) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// the name `logs show` takes for a log file
+fn log_name(path: &Path) -> String {
+    let file = path.file_name().unwrap().to_string_lossy();
+    let stem = file.trim_end_matches(".gz").trim_end_matches(".log");
+    let parent = path.parent().unwrap();
+    if parent == game_logs_dir() {
+        format!("{}{}", GAME_PREFIX, stem)
+    } else {
+        format!("{}/{}", parent.file_name().unwrap().to_string_lossy(), stem)
+    }
+}
+
+/// finds a log by its name from `logs list`, or by its path
+fn find_log(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    let base = match name.strip_prefix(GAME_PREFIX) {
+        Some(stem) => game_logs_dir().join(stem),
+        None => launches_dir().join(name),
+    };
+    [".log", ".log.gz"]
+        .iter()
+        .map(|ext| PathBuf::from(format!("{}{}", base.display(), ext)))
+        .find(|path| path.is_file())
+}
+
+/// launch logs of one or every instance, and the game's own logs
+fn all_logs(opt_instance: Option<&str>, game: bool) -> Vec<PathBuf> {
+    if game {
+        return log_files(&game_logs_dir());
+    }
+    match opt_instance {
+        Some(instance) => log_files(&launches_dir().join(instance)),
+        None => fs::read_dir(launches_dir())
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .flat_map(|e| log_files(&e.path()))
+            .collect(),
+    }
+}
+
+pub fn list(opt_instance: Option<String>, game: bool) {
+    let mut rows = vec![
+        vec![
+            "NAME".cell(),
+            "SIZE".cell(),
+            "MODIFIED".cell(),
+        ]
+    ];
+
+    for path in all_logs(opt_instance.as_deref(), game) {
+        let metadata = fs::metadata(&path).ok();
+        let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+        let modified = metadata
+            .and_then(|m| m.modified().ok())
+            .map(|time| chrono::DateTime::<chrono::Local>::from(time).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        let compressed = if path.extension().is_some_and(|ext| ext == "gz") { " (gz)" } else { "" };
+        rows.push(vec![log_name(&path).cell(), format!("{}{}", indicatif::HumanBytes(size), compressed).cell(), modified.cell()]);
+    }
+
+    let table = rows.table();
+    println!("{}", table.display().unwrap());
+}
+
+pub fn show(name: String, level: LogLevel) {
+    let Some(path) = find_log(&name) else {
+        eprintln!("FATAL: There's no log called {}, see `mc_cli logs list`.", name);
+        std::process::exit(-1);
+    };
+    let text = read_log(&path).expect("Failed to read log");
+    for line in log4j::format_plain(&text, level) {
+        println!("{}", line);
+    }
+}
+
+pub fn search(pattern: String, opt_instance: Option<String>, game: bool, ignore_case: bool) {
+    let regex = regex::RegexBuilder::new(&pattern).case_insensitive(ignore_case).build().unwrap_or_else(|err| {
+        eprintln!("FATAL: Invalid pattern: {}", err);
+        std::process::exit(-1);
+    });
+
+    let mut found = false;
+    for path in all_logs(opt_instance.as_deref(), game) {
+        let Ok(text) = read_log(&path) else { continue };
+        let name = log_name(&path);
+        for (number, line) in log4j::format_plain(&text, LogLevel::Trace).iter().enumerate() {
+            if regex.is_match(line) {
+                println!("{}:{}: {}", name, number + 1, line);
+                found = true;
+            }
+        }
+    }
+    if !found {
+        eprintln!("No matches");
+    }
+}
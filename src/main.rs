@@ -8,8 +8,27 @@ mod util;
 mod rules;
 mod assets;
 mod liteloader;
+mod resolve;
+mod server;
+mod properties;
+mod rcon;
+mod ping;
+mod query;
+mod instance;
+mod log4j;
+mod crash;
+mod running;
+mod logs;
+mod hooks;
+mod script;
+mod natives;
+mod maven;
+mod overrides;
+mod store;
+mod gc;
+mod process;
 
-use app::OpenTarget;
+use app::{InstanceCommand, LogsCommand, OpenTarget, ServerCommand, ServerConfigCommand};
 use clap::Parser;
 use cli_table::{Cell as _, Table};
 use version::UseQuilt;
@@ -19,18 +38,85 @@ async fn main() {
     let app = app::App::parse();
 
     match app.command {
-        app::Subcommand::Vanilla { version, mem } => {
-            vanilla::handle(version, mem, true, None).await;
+        app::Subcommand::Vanilla { version, mem, launch } => {
+            vanilla::handle(version, mem, true, launch).await;
         },
-        app::Subcommand::Fabric { version, loader_version, mem } => {
-            fabric::handle(version, loader_version, mem, UseQuilt::No).await;
+        app::Subcommand::Fabric { version, loader_version, mem, launch } => {
+            fabric::handle(version, loader_version, mem, UseQuilt::No, launch).await;
         },
-        app::Subcommand::Quilt { version, loader_version, mem, use_release } => {
-            fabric::handle(version, loader_version, mem, UseQuilt::Yes(use_release)).await;
+        app::Subcommand::Quilt { version, loader_version, mem, use_release, launch } => {
+            fabric::handle(version, loader_version, mem, UseQuilt::Yes(use_release), launch).await;
         },
-        app::Subcommand::Liteloader { version, loader_version, mem } => {
-            eprintln!("Liteloader isn't implemented yet. Please consider using fabric,quilt,or just running vanilla");
-            liteloader::handle(version, loader_version, mem).await;
+        app::Subcommand::Liteloader { version, loader_version, mem, launch } => {
+            liteloader::handle(version, loader_version, mem, launch).await;
+        },
+        app::Subcommand::Custom { json, mem, launch } => {
+            resolve::handle(json, mem, launch).await;
+        },
+        app::Subcommand::Server { command: ServerCommand::Install { version, loader, loader_version, name, accept_eula } } => {
+            server::install(version, loader, loader_version, name, accept_eula).await;
+        },
+        app::Subcommand::Server { command: ServerCommand::Start { name, mem, accept_eula } } => {
+            server::start(name, mem, accept_eula).await;
+        },
+        app::Subcommand::Server { command: ServerCommand::Stop { name } } => {
+            server::stop(name);
+        },
+        app::Subcommand::Server { command: ServerCommand::Config { command: ServerConfigCommand::Get { key, name } } } => {
+            properties::get(name, key);
+        },
+        app::Subcommand::Server { command: ServerCommand::Config { command: ServerConfigCommand::Set { key, value, name } } } => {
+            properties::set(name, key, value);
+        },
+        app::Subcommand::Server { command: ServerCommand::Config { command: ServerConfigCommand::List { name } } } => {
+            properties::list(name);
+        },
+        app::Subcommand::Rcon { address, command, password, server } => {
+            rcon::handle(address, command, password, server);
+        },
+        app::Subcommand::Ping { address, json } => {
+            ping::handle(address, json).await;
+        },
+        app::Subcommand::Query { address, basic, json, server } => {
+            query::handle(address, basic, json, server);
+        },
+        app::Subcommand::Instance { command: InstanceCommand::Get { instance, key } } => {
+            instance::get(instance, key);
+        },
+        app::Subcommand::Instance { command: InstanceCommand::Set { instance, key, value } } => {
+            instance::set(instance, key, value);
+        },
+        app::Subcommand::Instance { command: InstanceCommand::List { instance } } => {
+            instance::list(instance);
+        },
+        app::Subcommand::ExportLaunchScript { instance, output, format, mem, launch } => {
+            mem::check_if_valid(mem.clone());
+            script::export(instance, output, format, mem, launch);
+        },
+        app::Subcommand::Ps => {
+            running::ps();
+        },
+        app::Subcommand::Logs { command: Some(LogsCommand::List { instance, game }), .. } => {
+            logs::list(instance, game);
+        },
+        app::Subcommand::Logs { command: Some(LogsCommand::Show { name, log_level }), .. } => {
+            logs::show(name, log_level);
+        },
+        app::Subcommand::Logs { command: Some(LogsCommand::Search { pattern, instance, game, ignore_case }), .. } => {
+            logs::search(pattern, instance, game, ignore_case);
+        },
+        app::Subcommand::Logs { command: None, instance: Some(instance), follow, log_level } => {
+            running::logs(instance, follow, log_level);
+        },
+        app::Subcommand::Logs { command: None, instance: None, .. } => {
+            eprintln!("FATAL: Pass an instance, or one of list, show and search.");
+            std::process::exit(-1);
+        },
+        app::Subcommand::Kill { instance } => {
+            running::kill(instance);
+        },
+        app::Subcommand::Gc { dry_run } => {
+            gc::run(dry_run);
         },
         app::Subcommand::Open { target: OpenTarget::Game } => {
             let dirs = directories::ProjectDirs::from("me", "illia", "mc_cli").unwrap();
@@ -0,0 +1,129 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::version::MavenMetadataRoot;
+
+pub const MOJANG_LIBRARIES: &str = "https://libraries.minecraft.net/";
+
+/// where libraries get looked for after the repository they name, in this order
+pub const REPOSITORIES: &[&str] = &[
+    MOJANG_LIBRARIES,
+    "https://maven.fabricmc.net/",
+    "https://maven.quiltmc.org/repository/release/",
+    "https://maven.minecraftforge.net/",
+    "https://repo1.maven.org/maven2/",
+];
+
+/// A maven coordinate, `group:artifact:version[:classifier][@extension]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coordinate {
+    pub group: String,
+    pub artifact: String,
+    pub version: String,
+    pub classifier: Option<String>,
+    /// the packaging, `jar` unless it's given after an @
+    pub extension: String,
+}
+
+impl FromStr for Coordinate {
+    type Err = String;
+
+    fn from_str(coords: &str) -> Result<Coordinate, String> {
+        let (name, extension) = coords.split_once('@').unwrap_or((coords, "jar"));
+        let parts = name.split(':').collect::<Vec<_>>();
+        if !(3..=4).contains(&parts.len()) || parts.iter().any(|part| part.is_empty()) || extension.is_empty() {
+            return Err(format!("Invalid maven coordinate {}, expected group:artifact:version[:classifier][@extension]", coords));
+        }
+
+        Ok(Coordinate {
+            group: parts[0].to_owned(),
+            artifact: parts[1].to_owned(),
+            version: parts[2].to_owned(),
+            classifier: parts.get(3).map(|classifier| (*classifier).to_owned()),
+            extension: extension.to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.group, self.artifact, self.version)?;
+        if let Some(classifier) = &self.classifier {
+            write!(f, ":{}", classifier)?;
+        }
+        if self.extension != "jar" {
+            write!(f, "@{}", self.extension)?;
+        }
+        Ok(())
+    }
+}
+
+impl Coordinate {
+    /// the dir of this version of the artifact in a repository
+    fn dir(&self) -> String {
+        format!("{}/{}/{}", self.group.replace('.', "/"), self.artifact, self.version)
+    }
+
+    fn file_name(&self, version: &str) -> String {
+        match &self.classifier {
+            Some(classifier) => format!("{}-{}-{}.{}", self.artifact, version, classifier, self.extension),
+            None => format!("{}-{}.{}", self.artifact, version, self.extension),
+        }
+    }
+
+    /// the path of the artifact relative to a repository, or to the libs dir
+    pub fn path(&self) -> String {
+        format!("{}/{}", self.dir(), self.file_name(&self.version))
+    }
+
+    /// group:artifact along with the classifier, which is the same for every version of a library
+    pub fn key(&self) -> String {
+        match &self.classifier {
+            Some(classifier) => format!("{}:{}:{}", self.group, self.artifact, classifier),
+            None => format!("{}:{}", self.group, self.artifact),
+        }
+    }
+
+    pub fn is_snapshot(&self) -> bool {
+        self.version.ends_with("-SNAPSHOT")
+    }
+
+    /// the url of the artifact in `repository`, snapshots are looked up in the version's maven-metadata.xml
+    /// since they're published under the timestamp of the build instead
+    pub async fn url(&self, repository: &str) -> Result<String, Box<dyn Error>> {
+        let repository = repository.trim_end_matches('/');
+        if !self.is_snapshot() {
+            return Ok(format!("{}/{}", repository, self.path()));
+        }
+
+        let metadata_url = format!("{}/{}/maven-metadata.xml", repository, self.dir());
+        let text = reqwest::get(&metadata_url).await?.error_for_status()?.text().await?;
+        let metadata: MavenMetadataRoot = serde_xml_rs::from_str(&text)?;
+        let snapshot = metadata.versioning.snapshot.ok_or_else(|| format!("{} lists no snapshot builds", metadata_url))?;
+        let version = format!("{}-{}-{}", self.version.trim_end_matches("-SNAPSHOT"), snapshot.timestamp, snapshot.buildNumber);
+        Ok(format!("{}/{}/{}", repository, self.dir(), self.file_name(&version)))
+    }
+}
+
+/// the repository a library names, followed by the default ones
+pub fn repositories(preferred: Option<&str>) -> Vec<String> {
+    let mut repositories: Vec<String> = vec![];
+    for repository in preferred.into_iter().chain(REPOSITORIES.iter().copied()) {
+        let repository = format!("{}/", repository.trim_end_matches('/'));
+        if !repositories.contains(&repository) {
+            repositories.push(repository);
+        }
+    }
+    repositories
+}
+
+/// the url of the artifact in the first of `repositories` that has it
+pub async fn find(coordinate: &Coordinate, repositories: &[String]) -> Result<String, Box<dyn Error>> {
+    let client = reqwest::Client::new();
+    for repository in repositories {
+        let Ok(url) = coordinate.url(repository).await else { continue };
+        if client.head(&url).send().await.is_ok_and(|resp| resp.status().is_success()) {
+            return Ok(url);
+        }
+    }
+    Err(format!("None of the repositories have {}, tried {}", coordinate, repositories.join(", ")).into())
+}
@@ -0,0 +1,125 @@
+use std::{error::Error, fs, path::{Component, Path, PathBuf}};
+
+use crate::{overrides, resolve::ResolvedVersion, rules, util, version::Library};
+
+/// entries of natives jars that are never extracted, the signatures would only be noise in the natives dir
+const ALWAYS_EXCLUDED: &[&str] = &["META-INF/"];
+
+/// A jar whose natives get extracted, along with the prefixes of the entries left out
+struct NativesJar {
+    path: PathBuf,
+    /// from the version JSON, jars listed without one (like the natives replacements) get hashed
+    sha1: Option<String>,
+    exclude: Vec<String>,
+    /// the natives-* artifacts keep their natives in `<os>/<arch>/org/lwjgl/`, which java.library.path doesn't look into
+    flatten: bool,
+}
+
+/// whether the library is one of the `natives-<os>` artifacts versions using LWJGL 3.3 (1.19+) list on their own
+fn is_natives_artifact(library: &Library) -> bool {
+    library.coordinate().is_ok_and(|coordinate| coordinate.classifier.is_some_and(|classifier| classifier.starts_with("natives-")))
+}
+
+/// the natives jars of the version for this platform, looked up in the libs dirs of its chain
+fn natives_jars(resolved: &ResolvedVersion) -> Vec<NativesJar> {
+    let mut jars = vec![];
+    for library in resolved.json.libraries.iter().filter(|library| rules::library_allowed(library)) {
+        let (paths, flatten): (Vec<(String, Option<String>)>, bool) = match &library.downloads.classifiers {
+            Some(classifiers) => (rules::classifiers_needed(classifiers).into_iter().map(|download| (download.path.clone(), Some(download.sha1.clone()))).collect(), false),
+            None if is_natives_artifact(library) => {
+                let sha1 = library.downloads.artifact.as_ref().map(|artifact| artifact.sha1.clone());
+                (library.artifact().ok().flatten().map(|(path, _)| (path, sha1)).into_iter().collect(), true)
+            },
+            None => (vec![], false),
+        };
+
+        for (path, sha1) in paths {
+            let Some(found) = resolved.chain.iter().map(|dir| dir.join("libs").join(&path)).find(|found| found.is_file()) else {
+                eprintln!("Warning: The natives jar {} isn't downloaded, try reinstalling the version", path);
+                continue;
+            };
+            jars.push(NativesJar {
+                path: found,
+                sha1,
+                exclude: library.extract.as_ref().map(|extract| extract.exclude.clone()).unwrap_or_default(),
+                flatten,
+            });
+        }
+    }
+    jars
+}
+
+/// the dir the natives of the version get extracted to, named after the contents of the jars they come from
+/// so versions with the same natives share it, and changed jars get extracted again
+pub fn natives_dir(resolved: &ResolvedVersion) -> PathBuf {
+    let mut key = natives_jars(resolved)
+        .iter()
+        .map(|jar| {
+            let sha1 = jar.sha1.clone().unwrap_or_else(|| util::sha1_hex(&fs::read(&jar.path).unwrap_or_default()));
+            format!("{}|{}|{}", sha1.to_lowercase(), jar.flatten, jar.exclude.join(","))
+        })
+        .collect::<Vec<_>>();
+    key.extend(overrides::system_lwjgl2_natives(&resolved.json.libraries).iter().map(|path| path.display().to_string()));
+    key.sort();
+    let hash = util::sha1_hex(key.join("\n").as_bytes());
+    resolved.data_dir().join("natives").join(&hash[..16])
+}
+
+/// extracts the natives of the version unless they already are, and returns the dir they're in
+pub fn extract(resolved: &ResolvedVersion) -> Result<PathBuf, Box<dyn Error>> {
+    let dir = natives_dir(resolved);
+    if dir.is_dir() {
+        return Ok(dir);
+    }
+
+    // extracted next to it and renamed when done, so an interrupted extraction never looks finished
+    let partial = dir.with_extension("partial");
+    let _ = fs::remove_dir_all(&partial);
+    fs::create_dir_all(&partial)?;
+
+    for natives_jar in natives_jars(resolved) {
+        let jar = jars::jar(&natives_jar.path, jars::JarOptionBuilder::default())
+            .map_err(|err| format!("Failed to read {}: {}", natives_jar.path.display(), err))?;
+        for (name, content) in jar.files {
+            // zip entries always use /, but the name comes back with the separator of the platform
+            let name = name.replace('\\', "/");
+            let excluded = ALWAYS_EXCLUDED
+                .iter()
+                .copied()
+                .chain(natives_jar.exclude.iter().map(String::as_str))
+                .any(|prefix| name.starts_with(prefix));
+            if excluded {
+                continue;
+            }
+            // an entry like ../../x would be written outside the natives dir
+            let escapes = Path::new(&name).components().any(|component| matches!(component, Component::ParentDir | Component::RootDir | Component::Prefix(_)));
+            if escapes {
+                return Err(format!("{} has an entry outside the jar, {}", natives_jar.path.display(), name).into());
+            }
+            let path = if natives_jar.flatten {
+                partial.join(name.rsplit('/').next().unwrap())
+            } else {
+                partial.join(&name)
+            };
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(&path, content)?;
+        }
+    }
+
+    // a replacement for LWJGL 2's natives wins over the ones of the system
+    let has_lwjgl = fs::read_dir(&partial)?.flatten().any(|entry| entry.file_name().to_string_lossy().starts_with("liblwjgl"));
+    if !has_lwjgl {
+        for path in overrides::system_lwjgl2_natives(&resolved.json.libraries) {
+            fs::copy(&path, partial.join(path.file_name().unwrap()))?;
+        }
+    }
+
+    if let Err(err) = fs::rename(&partial, &dir) {
+        // another launch extracted the same natives in the meantime
+        let _ = fs::remove_dir_all(&partial);
+        if !dir.is_dir() {
+            return Err(err.into());
+        }
+    }
+    Ok(dir)
+}
@@ -0,0 +1,254 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use directories::ProjectDirs;
+use serde::Deserialize;
+
+use crate::{maven::Coordinate, rules::Platform, version::{Library, LibraryDownloads}};
+
+const CONFIG_FILE: &str = "natives_overrides.json";
+/// LWJGL 3 publishes natives for every platform it supports here
+const MAVEN_CENTRAL: &str = "https://repo1.maven.org/maven2/";
+/// LWJGL 2 never had natives for these platforms, but Debian and Ubuntu build it for them as liblwjgl-java-jni,
+/// which puts the natives here
+const SYSTEM_JNI_DIR: &str = "/usr/lib/jni";
+
+/// Replacements for natives Mojang doesn't build for the platform, saved as natives_overrides.json in the data dir.
+/// This one downloads the LWJGL 3 natives from a Maven Central mirror, and leaves the narrator natives of 1.12 out
+/// ```json
+/// {
+///   "repository": "https://maven.aliyun.com/repository/public/",
+///   "libraries": {
+///     "linux-aarch64": {
+///       "com.mojang:text2speech:1.10.3": ""
+///     }
+///   }
+/// }
+/// ```
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct OverridesConfig {
+    /// where the replacements are downloaded from before the usual repositories, Maven Central by default
+    pub repository: Option<String>,
+    /// by platform, then by the coordinate of the library to replace, the coordinate of its replacement
+    /// or an empty string to leave the library out
+    pub libraries: HashMap<String, HashMap<String, String>>,
+}
+
+/// the platforms natives get replaced on, and the suffix LWJGL 3 gives their natives classifiers
+fn lwjgl_suffix(platform: &Platform) -> Option<&'static str> {
+    match (platform.name, platform.arch) {
+        ("linux", "aarch64") => Some("arm64"),
+        ("linux", "riscv64") => Some("riscv64"),
+        _ => None,
+    }
+}
+
+fn load() -> OverridesConfig {
+    let proj_dirs = ProjectDirs::from("me", "illia", "mc_cli").unwrap();
+    let path = proj_dirs.data_dir().join(CONFIG_FILE);
+    match fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text).unwrap_or_else(|err| {
+            eprintln!("Warning: Ignoring invalid {}: {}", path.display(), err);
+            OverridesConfig::default()
+        }),
+        Err(_) => OverridesConfig::default(),
+    }
+}
+
+/// the coordinate of the linux natives a library brings, either as a natives-linux artifact or as classifiers
+fn linux_natives(library: &Library) -> Option<Coordinate> {
+    let coordinate = library.coordinate().ok()?;
+    if coordinate.classifier.as_deref() == Some("natives-linux") {
+        return Some(coordinate);
+    }
+    let classifiers = library.downloads.classifiers.as_ref()?;
+    (classifiers.natives_linux.is_some() || classifiers.natives_linux_64.is_some()).then_some(coordinate)
+}
+
+/// the LWJGL 2 natives the system has, which versions on LWJGL 2 use on platforms Mojang has no natives for
+/// unless a replacement brings its own
+pub fn system_lwjgl2_natives(libraries: &[Library]) -> Vec<PathBuf> {
+    let uses_lwjgl2 = libraries.iter().any(|library| library.coordinate().is_ok_and(|coordinate| coordinate.group == "org.lwjgl.lwjgl"));
+    if lwjgl_suffix(Platform::current()).is_none() || !uses_lwjgl2 {
+        return vec![];
+    }
+    let Ok(entries) = fs::read_dir(SYSTEM_JNI_DIR) else { return vec![] };
+    let mut natives = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.file_name().is_some_and(|name| {
+            let name = name.to_string_lossy();
+            name.starts_with("liblwjgl") && name.ends_with(".so")
+        }))
+        .collect::<Vec<_>>();
+    natives.sort();
+    natives
+}
+
+/// swaps the natives of the libraries for builds of this platform, if Mojang doesn't have any.
+/// The replacements are natives-* artifacts, so they're downloaded, extracted and put on the classpath like the ones of 1.19+
+pub fn apply(libraries: &[Library]) -> Vec<Library> {
+    let platform = Platform::current();
+    if lwjgl_suffix(platform).is_none() {
+        return libraries.to_vec();
+    }
+    apply_for(libraries, platform, &load())
+}
+
+fn apply_for(libraries: &[Library], platform: &Platform, config: &OverridesConfig) -> Vec<Library> {
+    let Some(suffix) = lwjgl_suffix(platform) else { return libraries.to_vec() };
+    let configured = config.libraries.get(&format!("{}-{}", platform.name, platform.arch));
+    let repository = config.repository.clone().unwrap_or(MAVEN_CENTRAL.to_owned());
+
+    let mut applied = vec![];
+    for library in libraries {
+        let Some(natives) = linux_natives(library) else {
+            applied.push(library.clone());
+            continue;
+        };
+
+        // the java part of the library is kept, only the natives get replaced
+        if library.downloads.classifiers.is_some() && library.downloads.artifact.is_some() {
+            applied.push(Library {
+                downloads: LibraryDownloads { artifact: library.downloads.artifact.clone(), classifiers: None },
+                extract: None,
+                ..library.clone()
+            });
+        }
+
+        let replacement = match configured.and_then(|configured| configured.get(&library.name)) {
+            Some(replacement) if replacement.is_empty() => None,
+            Some(replacement) => Some(replacement.clone()),
+            None if natives.group == "org.lwjgl" => Some(format!("{}:{}:{}:natives-linux-{}", natives.group, natives.artifact, natives.version, suffix)),
+            // LWJGL 2, whose natives come from the system
+            None => {
+                if natives.group == "org.lwjgl.lwjgl" && natives.artifact == "lwjgl-platform" && system_lwjgl2_natives(libraries).is_empty() {
+                    eprintln!(
+                        "Warning: There are no {}-{} natives for {}, install liblwjgl-java-jni or add a replacement to {}",
+                        platform.name, platform.arch, library.name, CONFIG_FILE
+                    );
+                }
+                None
+            },
+        };
+        if let Some(name) = replacement {
+            applied.push(Library {
+                downloads: LibraryDownloads::default(),
+                name,
+                url: Some(repository.clone()),
+                rules: library.rules.clone(),
+                extract: library.extract.clone(),
+            });
+        }
+    }
+    applied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn platform(name: &'static str, arch: &'static str) -> Platform {
+        Platform { name, arch, version: "6.8.0".to_owned() }
+    }
+
+    fn download(path: &str) -> String {
+        format!(r#"{{"path": "{}", "sha1": "0000000000000000000000000000000000000000", "size": 1, "url": "https://libraries.minecraft.net/{}"}}"#, path, path)
+    }
+
+    fn libraries(json: &str) -> Vec<Library> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn names(libraries: &[Library]) -> Vec<(&str, Option<&str>)> {
+        libraries.iter().map(|library| (library.name.as_str(), library.url.as_deref())).collect()
+    }
+
+    /// 1.12.2, LWJGL 2 with its natives in classifiers of a library of their own
+    fn lwjgl2() -> Vec<Library> {
+        libraries(&format!(
+            r#"[
+                {{"name": "org.lwjgl.lwjgl:lwjgl:2.9.4-nightly-20150209", "downloads": {{"artifact": {}}}}},
+                {{"name": "org.lwjgl.lwjgl:lwjgl-platform:2.9.4-nightly-20150209", "downloads": {{"classifiers": {{"natives-linux": {}, "natives-windows": {}}}}}, "extract": {{"exclude": ["META-INF/"]}}}}
+            ]"#,
+            download("org/lwjgl/lwjgl/lwjgl/2.9.4-nightly-20150209/lwjgl-2.9.4-nightly-20150209.jar"),
+            download("org/lwjgl/lwjgl/lwjgl-platform/2.9.4-nightly-20150209/lwjgl-platform-2.9.4-nightly-20150209-natives-linux.jar"),
+            download("org/lwjgl/lwjgl/lwjgl-platform/2.9.4-nightly-20150209/lwjgl-platform-2.9.4-nightly-20150209-natives-windows.jar"),
+        ))
+    }
+
+    #[test]
+    fn classifier_natives() {
+        let arm64 = platform("linux", "aarch64");
+        // 1.16.5, LWJGL 3 with the natives as classifiers of the java library
+        let lwjgl3 = libraries(&format!(
+            r#"[{{"name": "org.lwjgl:lwjgl:3.2.2", "downloads": {{"artifact": {}, "classifiers": {{"natives-linux": {}}}}}, "extract": {{"exclude": ["META-INF/"]}}}}]"#,
+            download("org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2.jar"),
+            download("org/lwjgl/lwjgl/3.2.2/lwjgl-3.2.2-natives-linux.jar"),
+        ));
+        let applied = apply_for(&lwjgl3, &arm64, &OverridesConfig::default());
+        assert_eq!(names(&applied), [("org.lwjgl:lwjgl:3.2.2", None), ("org.lwjgl:lwjgl:3.2.2:natives-linux-arm64", Some(MAVEN_CENTRAL))]);
+        assert!(applied[0].downloads.artifact.is_some() && applied[0].downloads.classifiers.is_none() && applied[0].extract.is_none());
+        assert_eq!(applied[1].extract.as_ref().unwrap().exclude, ["META-INF/"]);
+
+        // the natives of LWJGL 2 come from the system unless they're replaced
+        let applied = apply_for(&lwjgl2(), &arm64, &OverridesConfig::default());
+        assert_eq!(names(&applied), [("org.lwjgl.lwjgl:lwjgl:2.9.4-nightly-20150209", None)]);
+
+        let config = OverridesConfig {
+            repository: Some("http://localhost:8080/maven/".to_owned()),
+            libraries: HashMap::from([(
+                "linux-aarch64".to_owned(),
+                HashMap::from([("org.lwjgl.lwjgl:lwjgl-platform:2.9.4-nightly-20150209".to_owned(), "org.lwjgl.lwjgl:lwjgl-platform:2.9.4-nightly-20150209:natives-linux-arm64".to_owned())]),
+            )]),
+        };
+        let applied = apply_for(&lwjgl2(), &arm64, &config);
+        assert_eq!(names(&applied), [
+            ("org.lwjgl.lwjgl:lwjgl:2.9.4-nightly-20150209", None),
+            ("org.lwjgl.lwjgl:lwjgl-platform:2.9.4-nightly-20150209:natives-linux-arm64", Some("http://localhost:8080/maven/")),
+        ]);
+        assert!(applied[1].downloads.artifact.is_none() && applied[1].downloads.classifiers.is_none());
+
+        // riscv64 has no replacement configured
+        let applied = apply_for(&lwjgl2(), &platform("linux", "riscv64"), &config);
+        assert_eq!(names(&applied), [("org.lwjgl.lwjgl:lwjgl:2.9.4-nightly-20150209", None)]);
+    }
+
+    #[test]
+    fn natives_artifacts() {
+        // 1.21, the natives of LWJGL 3.3 are libraries of their own, one for each platform
+        let lwjgl = libraries(&format!(
+            r#"[
+                {{"name": "org.lwjgl:lwjgl:3.3.3", "downloads": {{"artifact": {}}}}},
+                {{"name": "org.lwjgl:lwjgl:3.3.3:natives-linux", "downloads": {{"artifact": {}}}, "rules": [{{"action": "allow", "os": {{"name": "linux"}}}}]}},
+                {{"name": "com.mojang:text2speech:1.17.9:natives-linux", "downloads": {{"artifact": {}}}, "rules": [{{"action": "allow", "os": {{"name": "linux"}}}}]}}
+            ]"#,
+            download("org/lwjgl/lwjgl/3.3.3/lwjgl-3.3.3.jar"),
+            download("org/lwjgl/lwjgl/3.3.3/lwjgl-3.3.3-natives-linux.jar"),
+            download("com/mojang/text2speech/1.17.9/text2speech-1.17.9-natives-linux.jar"),
+        ));
+
+        let applied = apply_for(&lwjgl, &platform("linux", "aarch64"), &OverridesConfig::default());
+        assert_eq!(names(&applied), [("org.lwjgl:lwjgl:3.3.3", None), ("org.lwjgl:lwjgl:3.3.3:natives-linux-arm64", Some(MAVEN_CENTRAL))]);
+        assert!(applied[1].downloads.artifact.is_none());
+        assert_eq!(applied[1].rules.as_ref().unwrap().len(), 1);
+
+        let config = OverridesConfig {
+            repository: None,
+            libraries: HashMap::from([(
+                "linux-riscv64".to_owned(),
+                HashMap::from([
+                    ("org.lwjgl:lwjgl:3.3.3:natives-linux".to_owned(), String::new()),
+                    ("com.mojang:text2speech:1.17.9:natives-linux".to_owned(), "com.mojang:text2speech:1.17.9:natives-linux-riscv64".to_owned()),
+                ]),
+            )]),
+        };
+        let applied = apply_for(&lwjgl, &platform("linux", "riscv64"), &config);
+        assert_eq!(names(&applied), [("org.lwjgl:lwjgl:3.3.3", None), ("com.mojang:text2speech:1.17.9:natives-linux-riscv64", Some(MAVEN_CENTRAL))]);
+
+        // Mojang has natives for x86_64
+        let applied = apply_for(&lwjgl, &platform("linux", "x86_64"), &config);
+        assert_eq!(names(&applied), names(&lwjgl));
+        assert!(applied[1].downloads.artifact.is_some());
+    }
+}
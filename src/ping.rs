@@ -0,0 +1,366 @@
+use std::{error::Error, net::IpAddr, time::{Duration, Instant}};
+
+use hickory_resolver::TokioAsyncResolver;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::{io::{AsyncRead, AsyncReadExt as _, AsyncWriteExt as _}, net::TcpStream};
+
+use crate::rcon;
+
+const DEFAULT_PORT: u16 = 25565;
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// -1 asks the server to answer with whatever protocol it speaks
+const PROTOCOL_VERSION: i32 = -1;
+
+#[derive(Serialize, Debug)]
+pub struct ServerStatus {
+    pub host: String,
+    pub port: u16,
+    pub version: String,
+    pub protocol: i32,
+    pub motd: String,
+    pub online: i64,
+    pub max: i64,
+    pub sample: Vec<String>,
+    pub latency_ms: Option<u128>,
+    /// whether the server only answered the pre-1.7 ping
+    pub legacy: bool,
+}
+
+pub fn write_varint(data: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7f == 0 {
+            data.push(value as u8);
+            return;
+        }
+        data.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+pub async fn read_varint(stream: &mut (impl AsyncRead + Unpin)) -> Result<i32, Box<dyn Error>> {
+    let mut value: u32 = 0;
+    for i in 0..5 {
+        let byte = stream.read_u8().await?;
+        value |= ((byte & 0x7f) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    Err("VarInt is too big".into())
+}
+
+fn write_string(data: &mut Vec<u8>, value: &str) {
+    write_varint(data, value.len() as i32);
+    data.extend(value.as_bytes());
+}
+
+/// prefixes the packet id and data with their length
+fn packet(id: i32, data: &[u8]) -> Vec<u8> {
+    let mut body = vec![];
+    write_varint(&mut body, id);
+    body.extend(data);
+    let mut packet = vec![];
+    write_varint(&mut packet, body.len() as i32);
+    packet.extend(body);
+    packet
+}
+
+/// reads a packet, returning its id and data
+async fn read_packet(stream: &mut TcpStream) -> Result<(i32, Vec<u8>), Box<dyn Error>> {
+    let len = read_varint(stream).await?;
+    if !(1..=1 << 21).contains(&len) {
+        return Err(format!("Invalid packet length {}", len).into());
+    }
+    let mut data = vec![0u8; len as usize];
+    stream.read_exact(&mut data).await?;
+
+    let mut id: u32 = 0;
+    let mut read = 0;
+    for (i, byte) in data.iter().enumerate().take(5) {
+        id |= ((byte & 0x7f) as u32) << (7 * i);
+        read = i + 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    Ok((id as i32, data[read..].to_vec()))
+}
+
+/// turns a chat component (or plain string) into plain text
+pub fn chat_to_text(chat: &Value) -> String {
+    match chat {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts.iter().map(chat_to_text).collect(),
+        Value::Object(component) => {
+            let mut text = component.get("text").and_then(Value::as_str).unwrap_or_default().to_owned();
+            if let Some(Value::Array(extra)) = component.get("extra") {
+                text.extend(extra.iter().map(chat_to_text));
+            }
+            text
+        },
+        _ => String::new(),
+    }
+}
+
+/// splits `host:port`, `[ipv6]:port` or a bare host or IPv6 address into the host and the port, if there is one
+pub fn split_address(address: &str) -> Result<(&str, Option<u16>), String> {
+    let (host, port) = if let Some(rest) = address.strip_prefix('[') && let Some((host, rest)) = rest.split_once(']') {
+        match rest {
+            "" => (host, None),
+            rest => (host, Some(rest.strip_prefix(':').unwrap_or(rest))),
+        }
+    } else {
+        match address.split_once(':') {
+            // more than one : is an IPv6 address without a port
+            Some((host, port)) if !port.contains(':') => (host, Some(port)),
+            _ => (address, None),
+        }
+    };
+    if host.is_empty() {
+        return Err(format!("{} has no host", address));
+    }
+    match port {
+        Some(port) => port.parse().map(|port| (host, Some(port))).map_err(|_| format!("Invalid port {:?} in {}", port, address)),
+        None => Ok((host, None)),
+    }
+}
+
+/// looks up the `_minecraft._tcp` SRV record, falling back to the host itself
+pub async fn resolve_address(address: &str) -> Result<(String, u16), String> {
+    let (host, port) = split_address(address)?;
+    if let Some(port) = port {
+        return Ok((host.to_owned(), port));
+    }
+
+    if host.parse::<IpAddr>().is_err()
+        && let Ok(resolver) = TokioAsyncResolver::tokio_from_system_conf()
+        && let Ok(records) = resolver.srv_lookup(format!("_minecraft._tcp.{}", host)).await
+        && let Some(record) = records.iter().next() {
+        let target = record.target().to_utf8();
+        return Ok((target.trim_end_matches('.').to_owned(), record.port()));
+    }
+
+    Ok((host.to_owned(), DEFAULT_PORT))
+}
+
+pub async fn ping(host: &str, port: u16) -> Result<ServerStatus, Box<dyn Error>> {
+    let mut stream = tokio::time::timeout(TIMEOUT, TcpStream::connect((host, port))).await??;
+
+    let mut handshake = vec![];
+    write_varint(&mut handshake, PROTOCOL_VERSION);
+    write_string(&mut handshake, host);
+    handshake.extend(port.to_be_bytes());
+    write_varint(&mut handshake, 1);
+    stream.write_all(&packet(0x00, &handshake)).await?;
+    stream.write_all(&packet(0x00, &[])).await?;
+
+    let (id, data) = tokio::time::timeout(TIMEOUT, read_packet(&mut stream)).await??;
+    if id != 0x00 {
+        return Err(format!("Unexpected status response packet {:#x}", id).into());
+    }
+    // skip the length of the string
+    let json_start = data.iter().position(|byte| byte & 0x80 == 0).ok_or("Invalid status response")? + 1;
+    let status: Value = serde_json::from_slice(&data[json_start..])?;
+
+    let sent = Instant::now();
+    let payload = chrono::Utc::now().timestamp_millis();
+    stream.write_all(&packet(0x01, &payload.to_be_bytes())).await?;
+    let latency_ms = match tokio::time::timeout(TIMEOUT, read_packet(&mut stream)).await {
+        Ok(Ok((0x01, _))) => Some(sent.elapsed().as_millis()),
+        _ => None,
+    };
+
+    let players = status.get("players");
+    Ok(ServerStatus {
+        host: host.to_owned(),
+        port,
+        version: status.pointer("/version/name").and_then(Value::as_str).unwrap_or_default().to_owned(),
+        protocol: status.pointer("/version/protocol").and_then(Value::as_i64).unwrap_or(-1) as i32,
+        motd: rcon::strip_colors(&chat_to_text(status.get("description").unwrap_or(&Value::Null))),
+        online: players.and_then(|p| p.get("online")).and_then(Value::as_i64).unwrap_or(0),
+        max: players.and_then(|p| p.get("max")).and_then(Value::as_i64).unwrap_or(0),
+        sample: players
+            .and_then(|p| p.get("sample"))
+            .and_then(Value::as_array)
+            .map(|sample| sample.iter().filter_map(|p| p.get("name").and_then(Value::as_str)).map(rcon::strip_colors).collect())
+            .unwrap_or_default(),
+        latency_ms,
+        legacy: false,
+    })
+}
+
+/// the 0xFE ping understood by servers before 1.7
+pub async fn ping_legacy(host: &str, port: u16) -> Result<ServerStatus, Box<dyn Error>> {
+    let mut stream = tokio::time::timeout(TIMEOUT, TcpStream::connect((host, port))).await??;
+    let sent = Instant::now();
+    stream.write_all(&[0xfe, 0x01]).await?;
+
+    let kick = tokio::time::timeout(TIMEOUT, stream.read_u8()).await??;
+    if kick != 0xff {
+        return Err(format!("Unexpected legacy ping response {:#x}", kick).into());
+    }
+    let latency_ms = Some(sent.elapsed().as_millis());
+    let len = stream.read_u16().await? as usize;
+    let mut utf16 = vec![0u16; len];
+    for c in utf16.iter_mut() {
+        *c = stream.read_u16().await?;
+    }
+    let text = String::from_utf16_lossy(&utf16);
+
+    let mut status = ServerStatus {
+        host: host.to_owned(),
+        port,
+        version: String::new(),
+        protocol: -1,
+        motd: String::new(),
+        online: 0,
+        max: 0,
+        sample: vec![],
+        latency_ms,
+        legacy: true,
+    };
+
+    if let Some(fields) = text.strip_prefix("§1\0") {
+        // 1.4 to 1.6: protocol, version, motd, online and max separated by nulls
+        let fields = fields.split('\0').collect::<Vec<_>>();
+        status.protocol = fields.first().and_then(|p| p.parse().ok()).unwrap_or(-1);
+        status.version = fields.get(1).unwrap_or(&"").to_string();
+        status.motd = rcon::strip_colors(fields.get(2).unwrap_or(&""));
+        status.online = fields.get(3).and_then(|p| p.parse().ok()).unwrap_or(0);
+        status.max = fields.get(4).and_then(|p| p.parse().ok()).unwrap_or(0);
+    } else {
+        // beta 1.8 to 1.3: motd§online§max
+        let fields = text.rsplitn(3, '§').collect::<Vec<_>>();
+        status.max = fields.first().and_then(|p| p.parse().ok()).unwrap_or(0);
+        status.online = fields.get(1).and_then(|p| p.parse().ok()).unwrap_or(0);
+        status.motd = fields.get(2).unwrap_or(&"").to_string();
+    }
+
+    Ok(status)
+}
+
+pub async fn handle(address: String, json: bool) {
+    let (host, port) = resolve_address(&address).await.unwrap_or_else(|err| {
+        eprintln!("FATAL: {}.", err);
+        std::process::exit(-1);
+    });
+
+    let status = match ping(&host, port).await {
+        Ok(status) => status,
+        Err(err) => match ping_legacy(&host, port).await {
+            Ok(status) => status,
+            Err(legacy_err) => {
+                eprintln!("FATAL: Failed to ping {}:{}: {} (legacy ping: {})", host, port, err, legacy_err);
+                std::process::exit(-1);
+            },
+        },
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&status).unwrap());
+        return;
+    }
+
+    println!("Server:   {}:{}", status.host, status.port);
+    println!("MOTD:     {}", status.motd.lines().collect::<Vec<_>>().join("\n          "));
+    println!("Version:  {}{}", status.version, if status.legacy { " (legacy ping)" } else { "" });
+    println!("Protocol: {}", status.protocol);
+    println!("Players:  {}/{}", status.online, status.max);
+    for name in &status.sample {
+        println!("          {}", name);
+    }
+    if let Some(latency) = status.latency_ms {
+        println!("Latency:  {}ms", latency);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn varint_round_trip() {
+        for (value, len) in [(0, 1), (1, 1), (127, 1), (128, 2), (25565, 3), (2097151, 3), (i32::MAX, 5), (-1, 5), (i32::MIN, 5)] {
+            let mut data = vec![];
+            write_varint(&mut data, value);
+            assert_eq!(data.len(), len, "{}", value);
+            assert_eq!(read_varint(&mut data.as_slice()).await.unwrap(), value);
+        }
+        assert!(read_varint(&mut [0xff; 6].as_slice()).await.is_err());
+    }
+
+    #[test]
+    fn splits_addresses() {
+        assert_eq!(split_address("mc.example.com"), Ok(("mc.example.com", None)));
+        assert_eq!(split_address("mc.example.com:25566"), Ok(("mc.example.com", Some(25566))));
+        assert_eq!(split_address("127.0.0.1:25565"), Ok(("127.0.0.1", Some(25565))));
+        assert_eq!(split_address("::1"), Ok(("::1", None)));
+        assert_eq!(split_address("[::1]:25565"), Ok(("::1", Some(25565))));
+        assert_eq!(split_address("[2001:db8::1]"), Ok(("2001:db8::1", None)));
+        assert!(split_address("mc.example.com:abc").is_err());
+        assert!(split_address("mc.example.com:70000").is_err());
+        assert!(split_address("[::1]:").is_err());
+        assert!(split_address(":25565").is_err());
+    }
+
+    #[tokio::test]
+    async fn pings_modern_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (id, handshake) = read_packet(&mut stream).await.unwrap();
+            assert_eq!(id, 0x00);
+            // protocol -1, "127.0.0.1", the port and the next state, status
+            assert_eq!(handshake.last(), Some(&1));
+            assert_eq!(read_packet(&mut stream).await.unwrap(), (0x00, vec![]));
+
+            let json = r#"{"version":{"name":"1.21.1","protocol":767},"players":{"max":20,"online":2,"sample":[{"name":"Alex","id":"0"},{"name":"§aSteve","id":"1"}]},"description":{"text":"A ","extra":[{"text":"§aMinecraft"},"Server"]}}"#;
+            let mut response = vec![];
+            write_string(&mut response, json);
+            stream.write_all(&packet(0x00, &response)).await.unwrap();
+
+            let (id, payload) = read_packet(&mut stream).await.unwrap();
+            assert_eq!(id, 0x01);
+            stream.write_all(&packet(0x01, &payload)).await.unwrap();
+        });
+
+        let status = ping("127.0.0.1", port).await.unwrap();
+        assert_eq!(status.version, "1.21.1");
+        assert_eq!(status.protocol, 767);
+        assert_eq!(status.motd, "A MinecraftServer");
+        assert_eq!((status.online, status.max), (2, 20));
+        assert_eq!(status.sample, ["Alex", "Steve"]);
+        assert!(status.latency_ms.is_some());
+        assert!(!status.legacy);
+    }
+
+    #[tokio::test]
+    async fn pings_legacy_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 2];
+            stream.read_exact(&mut request).await.unwrap();
+            assert_eq!(request, [0xfe, 0x01]);
+
+            let text = ["§1", "127", "1.6.4", "A §aMinecraft§r Server", "3", "20"].join("\0").encode_utf16().collect::<Vec<_>>();
+            let mut kick = vec![0xff];
+            kick.extend((text.len() as u16).to_be_bytes());
+            kick.extend(text.iter().flat_map(|c| c.to_be_bytes()));
+            stream.write_all(&kick).await.unwrap();
+        });
+
+        let status = ping_legacy("127.0.0.1", port).await.unwrap();
+        assert_eq!(status.protocol, 127);
+        assert_eq!(status.version, "1.6.4");
+        assert_eq!(status.motd, "A Minecraft Server");
+        assert_eq!((status.online, status.max), (3, 20));
+        assert!(status.legacy);
+    }
+}
@@ -0,0 +1,58 @@
+use std::{process::{Command, Stdio}, thread, time::Duration};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub fn is_alive(pid: u32) -> bool {
+    if cfg!(target_os = "windows") {
+        Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+            .unwrap_or(false)
+    } else {
+        Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+}
+
+/// asks the process to exit, servers save the world on SIGTERM.
+/// On windows this only reaches processes with a window, a console java.exe has to be killed with `force_kill`
+pub fn terminate(pid: u32) -> bool {
+    let status = if cfg!(target_os = "windows") {
+        Command::new("taskkill").args(["/T", "/PID", &pid.to_string()]).stdout(Stdio::null()).stderr(Stdio::null()).status()
+    } else {
+        Command::new("kill").arg(pid.to_string()).status()
+    };
+    matches!(status, Ok(status) if status.success())
+}
+
+/// kills the process along with its children
+pub fn force_kill(pid: u32) -> bool {
+    let status = if cfg!(target_os = "windows") {
+        Command::new("taskkill").args(["/T", "/F", "/PID", &pid.to_string()]).status()
+    } else {
+        Command::new("kill").args(["-9", &pid.to_string()]).status()
+    };
+    matches!(status, Ok(status) if status.success())
+}
+
+/// asks the process to exit, and kills it if it's still running after `timeout` or can't be asked at all.
+/// Returns whether it's gone
+pub fn stop(pid: u32, timeout: Duration) -> bool {
+    if terminate(pid) {
+        let mut waited = Duration::ZERO;
+        while is_alive(pid) && waited < timeout {
+            thread::sleep(POLL_INTERVAL);
+            waited += POLL_INTERVAL;
+        }
+    }
+    if is_alive(pid) {
+        eprintln!("The process (pid {}) didn't stop, killing it", pid);
+        return force_kill(pid);
+    }
+    true
+}
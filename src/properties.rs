@@ -0,0 +1,376 @@
+use std::{fs, path::Path};
+
+use cli_table::{Cell as _, Table as _};
+
+use crate::server;
+
+pub enum Kind {
+    Bool,
+    Int { min: i64, max: i64 },
+    /// one of the names, or its index for keys which used to be numeric
+    Enum(&'static [&'static str]),
+    String,
+}
+
+pub struct Key {
+    pub name: &'static str,
+    pub default: &'static str,
+    pub kind: Kind,
+}
+
+const MAX_INT: i64 = i32::MAX as i64;
+
+/// The keys of a vanilla server.properties with their defaults
+pub const KNOWN_KEYS: &[Key] = &[
+    Key { name: "accepts-transfers", default: "false", kind: Kind::Bool },
+    Key { name: "allow-flight", default: "false", kind: Kind::Bool },
+    Key { name: "allow-nether", default: "true", kind: Kind::Bool },
+    Key { name: "broadcast-console-to-ops", default: "true", kind: Kind::Bool },
+    Key { name: "broadcast-rcon-to-ops", default: "true", kind: Kind::Bool },
+    Key { name: "difficulty", default: "easy", kind: Kind::Enum(&["peaceful", "easy", "normal", "hard"]) },
+    Key { name: "enable-command-block", default: "false", kind: Kind::Bool },
+    Key { name: "enable-jmx-monitoring", default: "false", kind: Kind::Bool },
+    Key { name: "enable-query", default: "false", kind: Kind::Bool },
+    Key { name: "enable-rcon", default: "false", kind: Kind::Bool },
+    Key { name: "enable-status", default: "true", kind: Kind::Bool },
+    Key { name: "enforce-secure-profile", default: "true", kind: Kind::Bool },
+    Key { name: "enforce-whitelist", default: "false", kind: Kind::Bool },
+    Key { name: "entity-broadcast-range-percentage", default: "100", kind: Kind::Int { min: 10, max: 1000 } },
+    Key { name: "force-gamemode", default: "false", kind: Kind::Bool },
+    Key { name: "function-permission-level", default: "2", kind: Kind::Int { min: 1, max: 4 } },
+    Key { name: "gamemode", default: "survival", kind: Kind::Enum(&["survival", "creative", "adventure", "spectator"]) },
+    Key { name: "generate-structures", default: "true", kind: Kind::Bool },
+    Key { name: "generator-settings", default: "{}", kind: Kind::String },
+    Key { name: "hardcore", default: "false", kind: Kind::Bool },
+    Key { name: "hide-online-players", default: "false", kind: Kind::Bool },
+    Key { name: "initial-disabled-packs", default: "", kind: Kind::String },
+    Key { name: "initial-enabled-packs", default: "vanilla", kind: Kind::String },
+    Key { name: "level-name", default: "world", kind: Kind::String },
+    Key { name: "level-seed", default: "", kind: Kind::String },
+    Key { name: "level-type", default: "minecraft:normal", kind: Kind::String },
+    Key { name: "log-ips", default: "true", kind: Kind::Bool },
+    Key { name: "max-chained-neighbor-updates", default: "1000000", kind: Kind::Int { min: -1, max: MAX_INT } },
+    Key { name: "max-players", default: "20", kind: Kind::Int { min: 0, max: MAX_INT } },
+    Key { name: "max-tick-time", default: "60000", kind: Kind::Int { min: -1, max: MAX_INT } },
+    Key { name: "max-world-size", default: "29999984", kind: Kind::Int { min: 1, max: 29999984 } },
+    Key { name: "motd", default: "A Minecraft Server", kind: Kind::String },
+    Key { name: "network-compression-threshold", default: "256", kind: Kind::Int { min: -1, max: MAX_INT } },
+    Key { name: "online-mode", default: "true", kind: Kind::Bool },
+    Key { name: "op-permission-level", default: "4", kind: Kind::Int { min: 0, max: 4 } },
+    Key { name: "player-idle-timeout", default: "0", kind: Kind::Int { min: 0, max: MAX_INT } },
+    Key { name: "prevent-proxy-connections", default: "false", kind: Kind::Bool },
+    Key { name: "pvp", default: "true", kind: Kind::Bool },
+    Key { name: "query.port", default: "25565", kind: Kind::Int { min: 1, max: 65535 } },
+    Key { name: "rate-limit", default: "0", kind: Kind::Int { min: 0, max: MAX_INT } },
+    Key { name: "rcon.password", default: "", kind: Kind::String },
+    Key { name: "rcon.port", default: "25575", kind: Kind::Int { min: 1, max: 65535 } },
+    Key { name: "region-file-compression", default: "deflate", kind: Kind::Enum(&["deflate", "lz4", "none"]) },
+    Key { name: "require-resource-pack", default: "false", kind: Kind::Bool },
+    Key { name: "resource-pack", default: "", kind: Kind::String },
+    Key { name: "resource-pack-id", default: "", kind: Kind::String },
+    Key { name: "resource-pack-prompt", default: "", kind: Kind::String },
+    Key { name: "resource-pack-sha1", default: "", kind: Kind::String },
+    Key { name: "server-ip", default: "", kind: Kind::String },
+    Key { name: "server-port", default: "25565", kind: Kind::Int { min: 1, max: 65535 } },
+    Key { name: "simulation-distance", default: "10", kind: Kind::Int { min: 3, max: 32 } },
+    Key { name: "spawn-monsters", default: "true", kind: Kind::Bool },
+    Key { name: "spawn-protection", default: "16", kind: Kind::Int { min: 0, max: MAX_INT } },
+    Key { name: "sync-chunk-writes", default: "true", kind: Kind::Bool },
+    Key { name: "text-filtering-config", default: "", kind: Kind::String },
+    Key { name: "text-filtering-version", default: "0", kind: Kind::Int { min: 0, max: 1 } },
+    Key { name: "use-native-transport", default: "true", kind: Kind::Bool },
+    Key { name: "view-distance", default: "10", kind: Kind::Int { min: 3, max: 32 } },
+    Key { name: "white-list", default: "false", kind: Kind::Bool },
+];
+
+pub fn known_key(name: &str) -> Option<&'static Key> {
+    KNOWN_KEYS.iter().find(|key| key.name == name)
+}
+
+impl Key {
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        match &self.kind {
+            Kind::Bool => {
+                if value != "true" && value != "false" {
+                    return Err(format!("{} must be true or false", self.name));
+                }
+            },
+            Kind::Int { min, max } => {
+                let number: i64 = value.parse().map_err(|_| format!("{} must be a number", self.name))?;
+                if number < *min || number > *max {
+                    return Err(format!("{} must be between {} and {}", self.name, min, max));
+                }
+            },
+            Kind::Enum(names) => {
+                let is_index = value.parse::<usize>().map(|i| i < names.len()).unwrap_or(false);
+                if !names.contains(&value) && !is_index {
+                    return Err(format!("{} must be one of {}", self.name, names.join(", ")));
+                }
+            },
+            Kind::String => {},
+        }
+        Ok(())
+    }
+}
+
+enum Line {
+    /// comments and blank lines, kept as they are
+    Other(String),
+    /// `raw` is the line as read, which is written back as long as the value isn't changed
+    Entry { key: String, value: String, raw: Option<String> },
+}
+
+/// reads the java properties escapes: `\t`, `\n`, `\r`, `\f`, `\uXXXX`, and a backslash before any other character.
+/// It's collected as UTF-16 since characters outside the BMP are escaped as two \u surrogates
+fn unescape(text: &str) -> String {
+    let mut units = vec![];
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next() {
+                Some('t') => '\t',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('f') => '\u{c}',
+                Some('u') => {
+                    let hex = chars.by_ref().take(4).collect::<String>();
+                    match u16::from_str_radix(&hex, 16) {
+                        Ok(unit) => units.push(unit),
+                        Err(_) => units.extend(hex.encode_utf16()),
+                    }
+                    continue;
+                },
+                Some(c) => c,
+                None => break,
+            },
+            c => c,
+        };
+        units.extend(c.encode_utf16(&mut [0; 2]).iter());
+    }
+    String::from_utf16_lossy(&units)
+}
+
+/// escapes text the way java's Properties.store does, spaces only need it in keys and at the start of values
+fn escape(text: &str, is_key: bool) -> String {
+    let mut escaped = String::new();
+    for (i, c) in text.chars().enumerate() {
+        match c {
+            '\\' | '=' | ':' | '#' | '!' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            ' ' if is_key || i == 0 => escaped.push_str("\\ "),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\u{c}' => escaped.push_str("\\f"),
+            c if c.is_ascii() && !c.is_ascii_control() => escaped.push(c),
+            c => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    escaped.push_str(&format!("\\u{:04X}", unit));
+                }
+            },
+        }
+    }
+    escaped
+}
+
+/// splits a line into its key and value, the key ends at the first unescaped `=`, `:` or whitespace
+fn split_entry(line: &str) -> (String, String) {
+    let mut key_end = line.len();
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '=' || c == ':' || c.is_whitespace() {
+            key_end = i;
+            break;
+        }
+    }
+
+    let rest = line[key_end..].trim_start();
+    let rest = rest.strip_prefix(['=', ':']).map_or(rest, str::trim_start);
+    (unescape(&line[..key_end]), unescape(rest))
+}
+
+/// A server.properties file which keeps its comments and key order when written back
+pub struct Properties {
+    lines: Vec<Line>,
+}
+
+impl Properties {
+    pub fn parse(text: &str) -> Properties {
+        let lines = text.lines().map(|line| {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('!') {
+                return Line::Other(line.to_owned());
+            }
+            let (key, value) = split_entry(trimmed);
+            Line::Entry { key, value, raw: Some(line.to_owned()) }
+        }).collect();
+        Properties { lines }
+    }
+
+    pub fn load(path: &Path) -> Properties {
+        Properties::parse(&fs::read_to_string(path).unwrap_or_default())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.lines.iter().find_map(|line| match line {
+            Line::Entry { key: k, value, .. } if k == key => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// the value of the key, or its default for known keys
+    pub fn get_or_default(&self, key: &str) -> String {
+        self.get(key).or(known_key(key).map(|known| known.default)).unwrap_or_default().to_owned()
+    }
+
+    /// the address to reach the server on this machine
+    pub fn local_host(&self) -> String {
+        match self.get_or_default("server-ip") {
+            ip if ip.is_empty() => "127.0.0.1".to_owned(),
+            ip => ip,
+        }
+    }
+
+    /// replaces the value in place, or appends the key if it isn't there yet
+    pub fn set(&mut self, key: &str, new_value: &str) {
+        for line in &mut self.lines {
+            if let Line::Entry { key: k, value, raw } = line && k == key {
+                *value = new_value.to_owned();
+                *raw = None;
+                return;
+            }
+        }
+        self.lines.push(Line::Entry { key: key.to_owned(), value: new_value.to_owned(), raw: None });
+    }
+
+    pub fn entries(&self) -> Vec<(&str, &str)> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Entry { key, value, .. } => Some((key.as_str(), value.as_str())),
+            Line::Other(_) => None,
+        }).collect()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for line in &self.lines {
+            match line {
+                Line::Other(line) | Line::Entry { raw: Some(line), .. } => text.push_str(line),
+                Line::Entry { key, value, raw: None } => text.push_str(&format!("{}={}", escape(key, true), escape(value, false))),
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+pub fn get(opt_name: Option<String>, key: String) {
+    let properties = Properties::load(&server::server_dir(opt_name).join("server.properties"));
+    match (properties.get(&key), known_key(&key)) {
+        (Some(value), _) => println!("{}", value),
+        (None, Some(known)) => {
+            eprintln!("{} isn't set, showing the default", key);
+            println!("{}", known.default);
+        },
+        (None, None) => {
+            eprintln!("FATAL: {} isn't set and isn't a known key.", key);
+            std::process::exit(-1);
+        },
+    }
+}
+
+pub fn set(opt_name: Option<String>, key: String, value: String) {
+    let path = server::server_dir(opt_name).join("server.properties");
+    match known_key(&key) {
+        Some(known) => {
+            if let Err(err) = known.validate(&value) {
+                eprintln!("FATAL: {}.", err);
+                std::process::exit(-1);
+            }
+        },
+        None => eprintln!("Warning: {} isn't a known key, setting it without validation", key),
+    }
+
+    let mut properties = Properties::load(&path);
+    properties.set(&key, &value);
+    fs::write(&path, properties.to_text()).expect("Failed to write server.properties");
+}
+
+pub fn list(opt_name: Option<String>) {
+    let properties = Properties::load(&server::server_dir(opt_name).join("server.properties"));
+
+    let mut rows = vec![
+        vec![
+            "KEY".cell(),
+            "VALUE".cell(),
+            "DEFAULT".cell(),
+        ]
+    ];
+
+    for (key, value) in properties.entries() {
+        let default = known_key(key).map(|known| known.default).unwrap_or("");
+        rows.push(vec![key.cell(), value.cell(), default.cell()]);
+    }
+
+    // keys missing from the file use their defaults
+    for known in KNOWN_KEYS.iter().filter(|known| properties.get(known.name).is_none()) {
+        rows.push(vec![known.name.cell(), format!("{} (default)", known.default).cell(), known.default.cell()]);
+    }
+
+    let table = rows.table();
+    println!("{}", table.display().unwrap());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER_PROPERTIES: &str = "\
+#Minecraft server properties
+#Mon Oct 19 00:00:00 UTC 2026
+level-type=minecraft\\:normal
+motd=\\u00A7aA \\u00E9t\\u00E9 server \\uD83D\\uDE00
+! old style comment
+
+server-port = 25565
+";
+
+    #[test]
+    fn unescapes_values() {
+        let properties = Properties::parse(SERVER_PROPERTIES);
+        assert_eq!(properties.get("level-type"), Some("minecraft:normal"));
+        assert_eq!(properties.get("motd"), Some("\u{a7}aA \u{e9}t\u{e9} server \u{1f600}"));
+        assert_eq!(properties.get("server-port"), Some("25565"));
+    }
+
+    #[test]
+    fn round_trip_keeps_comments_and_order() {
+        let mut properties = Properties::parse(SERVER_PROPERTIES);
+        assert_eq!(properties.to_text(), SERVER_PROPERTIES);
+
+        properties.set("level-type", "a:b=c\\d");
+        properties.set("motd", " \u{e9}t\u{e9}");
+        properties.set("new key", "x");
+        let text = properties.to_text();
+        assert_eq!(text, "\
+#Minecraft server properties
+#Mon Oct 19 00:00:00 UTC 2026
+level-type=a\\:b\\=c\\\\d
+motd=\\ \\u00E9t\\u00E9
+! old style comment
+
+server-port = 25565
+new\\ key=x
+");
+
+        let reparsed = Properties::parse(&text);
+        assert_eq!(reparsed.get("level-type"), Some("a:b=c\\d"));
+        assert_eq!(reparsed.get("motd"), Some(" \u{e9}t\u{e9}"));
+        assert_eq!(reparsed.get("new key"), Some("x"));
+    }
+}
@@ -0,0 +1,301 @@
+use std::{collections::BTreeMap, error::Error, net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs as _, UdpSocket}, time::Duration};
+
+use serde::Serialize;
+
+use crate::{ping, properties::Properties, rcon, server};
+
+const DEFAULT_PORT: u16 = 25565;
+const TIMEOUT: Duration = Duration::from_secs(5);
+const MAGIC: [u8; 2] = [0xfe, 0xfd];
+
+const TYPE_STAT: u8 = 0x00;
+const TYPE_HANDSHAKE: u8 = 0x09;
+
+#[derive(Serialize, Debug)]
+pub struct BasicStat {
+    pub motd: String,
+    pub gametype: String,
+    pub map: String,
+    pub numplayers: i64,
+    pub maxplayers: i64,
+    pub hostport: u16,
+    pub hostip: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FullStat {
+    /// every key the server sent (hostname, version, plugins, map...)
+    pub info: BTreeMap<String, String>,
+    /// the server mod and its plugins, parsed from the `plugins` key
+    pub server_mod: Option<String>,
+    pub plugins: Vec<String>,
+    pub players: Vec<String>,
+}
+
+pub struct QueryClient {
+    socket: UdpSocket,
+    session_id: i32,
+    token: i32,
+}
+
+/// splits off a null terminated string
+fn read_string(data: &[u8]) -> (String, &[u8]) {
+    match data.iter().position(|byte| *byte == 0) {
+        Some(end) => (String::from_utf8_lossy(&data[..end]).to_string(), &data[end + 1..]),
+        None => (String::from_utf8_lossy(data).to_string(), &[]),
+    }
+}
+
+impl QueryClient {
+    /// connects and does the handshake to get a challenge token
+    pub fn connect(host: &str, port: u16) -> Result<QueryClient, Box<dyn Error>> {
+        let address = (host, port).to_socket_addrs()?.next().ok_or_else(|| format!("{} has no address", host))?;
+        // the local socket has to be of the same family as the server's address
+        let local: SocketAddr = if address.is_ipv6() { (Ipv6Addr::UNSPECIFIED, 0).into() } else { (Ipv4Addr::UNSPECIFIED, 0).into() };
+        let socket = UdpSocket::bind(local)?;
+        socket.set_read_timeout(Some(TIMEOUT))?;
+        socket.connect(address)?;
+
+        // only the lower 4 bits of each byte are used by the server
+        let session_id = (std::process::id() as i32) & 0x0f0f0f0f;
+        let mut client = QueryClient { socket, session_id, token: 0 };
+
+        let response = client.request(TYPE_HANDSHAKE, &[])?;
+        let (token, _) = read_string(&response);
+        client.token = token.trim().parse().map_err(|_| format!("Invalid challenge token {:?}", token))?;
+
+        Ok(client)
+    }
+
+    /// sends a request and returns the response payload after the type and session id
+    fn request(&self, r#type: u8, payload: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut packet = MAGIC.to_vec();
+        packet.push(r#type);
+        packet.extend(self.session_id.to_be_bytes());
+        packet.extend(payload);
+        self.socket.send(&packet)?;
+
+        let mut buf = [0u8; 65535];
+        let len = self.socket.recv(&mut buf)?;
+        if len < 5 || buf[0] != r#type || i32::from_be_bytes(buf[1..5].try_into().unwrap()) != self.session_id {
+            return Err("Invalid query response".into());
+        }
+        Ok(buf[5..len].to_vec())
+    }
+
+    pub fn basic_stat(&self) -> Result<BasicStat, Box<dyn Error>> {
+        let data = self.request(TYPE_STAT, &self.token.to_be_bytes())?;
+        let (motd, data) = read_string(&data);
+        let (gametype, data) = read_string(data);
+        let (map, data) = read_string(data);
+        let (numplayers, data) = read_string(data);
+        let (maxplayers, data) = read_string(data);
+        if data.len() < 2 {
+            return Err("Truncated basic stat response".into());
+        }
+        // the only little endian field of the protocol
+        let hostport = u16::from_le_bytes([data[0], data[1]]);
+        let (hostip, _) = read_string(&data[2..]);
+
+        Ok(BasicStat {
+            motd: rcon::strip_colors(&motd),
+            gametype,
+            map,
+            numplayers: numplayers.parse().unwrap_or(0),
+            maxplayers: maxplayers.parse().unwrap_or(0),
+            hostport,
+            hostip,
+        })
+    }
+
+    pub fn full_stat(&self) -> Result<FullStat, Box<dyn Error>> {
+        let mut payload = self.token.to_be_bytes().to_vec();
+        payload.extend([0, 0, 0, 0]);
+        let data = self.request(TYPE_STAT, &payload)?;
+
+        // "splitnum\0\x80\0"
+        let mut data = data.get(11..).ok_or("Truncated full stat response")?;
+        let mut info = BTreeMap::new();
+        loop {
+            let (key, rest) = read_string(data);
+            if key.is_empty() {
+                data = rest;
+                break;
+            }
+            let (value, rest) = read_string(rest);
+            info.insert(key, value);
+            data = rest;
+        }
+
+        // "\x01player_\0\0"
+        let mut data = data.get(10..).unwrap_or(&[]);
+        let mut players = vec![];
+        while !data.is_empty() {
+            let (name, rest) = read_string(data);
+            if name.is_empty() {
+                break;
+            }
+            players.push(name);
+            data = rest;
+        }
+
+        if let Some(hostname) = info.get_mut("hostname") {
+            *hostname = rcon::strip_colors(hostname);
+        }
+
+        // "ServerMod 1.0: Plugin 1.0; Other 2.0", empty on vanilla
+        let plugins_value = info.get("plugins").cloned().unwrap_or_default();
+        let (server_mod, plugins) = match plugins_value.split_once(':') {
+            Some((server_mod, plugins)) => (
+                Some(server_mod.trim().to_owned()),
+                plugins.split(';').map(|p| p.trim().to_owned()).filter(|p| !p.is_empty()).collect(),
+            ),
+            None if !plugins_value.is_empty() => (Some(plugins_value.trim().to_owned()), vec![]),
+            None => (None, vec![]),
+        };
+
+        Ok(FullStat { info, server_mod, plugins, players })
+    }
+}
+
+pub fn handle(opt_address: Option<String>, basic: bool, json: bool, opt_server: Option<String>) {
+    let (host, port) = if let Some(address) = opt_address {
+        match ping::split_address(&address) {
+            Ok((host, port)) => (host.to_owned(), port.unwrap_or(DEFAULT_PORT)),
+            Err(err) => {
+                eprintln!("FATAL: {}.", err);
+                std::process::exit(-1);
+            },
+        }
+    } else {
+        // without an address, query a server managed by mc_cli
+        let dir = server::server_dir(opt_server);
+        let props = Properties::load(&dir.join("server.properties"));
+        if props.get_or_default("enable-query") != "true" {
+            eprintln!("FATAL: query isn't enabled, turn it on with `mc_cli server config set enable-query true`.");
+            std::process::exit(-1);
+        }
+        let Ok(port) = props.get_or_default("query.port").parse() else {
+            eprintln!("FATAL: Invalid query.port in server.properties, set it with `mc_cli server config set query.port <port>`.");
+            std::process::exit(-1);
+        };
+        (props.local_host(), port)
+    };
+
+    let client = match QueryClient::connect(&host, port) {
+        Ok(client) => client,
+        Err(err) => {
+            eprintln!("FATAL: Failed to query {}:{}: {}", host, port, err);
+            std::process::exit(-1);
+        },
+    };
+
+    if basic {
+        let stat = client.basic_stat().unwrap_or_else(|err| {
+            eprintln!("FATAL: Failed to query {}:{}: {}", host, port, err);
+            std::process::exit(-1);
+        });
+        if json {
+            println!("{}", serde_json::to_string_pretty(&stat).unwrap());
+            return;
+        }
+        println!("MOTD:     {}", stat.motd);
+        println!("Gametype: {}", stat.gametype);
+        println!("Map:      {}", stat.map);
+        println!("Players:  {}/{}", stat.numplayers, stat.maxplayers);
+        println!("Address:  {}:{}", stat.hostip, stat.hostport);
+        return;
+    }
+
+    let stat = client.full_stat().unwrap_or_else(|err| {
+        eprintln!("FATAL: Failed to query {}:{}: {}", host, port, err);
+        std::process::exit(-1);
+    });
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stat).unwrap());
+        return;
+    }
+
+    for (key, value) in &stat.info {
+        if key != "plugins" {
+            println!("{:<11} {}", format!("{}:", key), value);
+        }
+    }
+    if let Some(server_mod) = &stat.server_mod {
+        println!("{:<11} {}", "server mod:", server_mod);
+    }
+    println!("plugins ({}):", stat.plugins.len());
+    for plugin in &stat.plugins {
+        println!("  {}", plugin);
+    }
+    println!("players ({}):", stat.players.len());
+    for player in &stat.players {
+        println!("  {}", player);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    const TOKEN: i32 = 9513307;
+
+    /// answers like a vanilla server with enable-query on, checking the challenge token of stat requests
+    fn fake_server() -> u16 {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let mut buf = [0u8; 1500];
+            while let Ok((len, from)) = socket.recv_from(&mut buf) {
+                let request = &buf[..len];
+                assert_eq!(request[..2], MAGIC);
+                let mut response = vec![request[2]];
+                response.extend(&request[3..7]);
+                match (request[2], len) {
+                    (TYPE_HANDSHAKE, 7) => response.extend(format!("{}\0", TOKEN).as_bytes()),
+                    (TYPE_STAT, 11) => {
+                        assert_eq!(request[7..11], TOKEN.to_be_bytes());
+                        response.extend(b"A \xc2\xa7aMinecraft Server\0SMP\0world\x002\x0020\0");
+                        response.extend(25565u16.to_le_bytes());
+                        response.extend(b"127.0.0.1\0");
+                    },
+                    (TYPE_STAT, 15) => {
+                        assert_eq!(request[7..11], TOKEN.to_be_bytes());
+                        response.extend(b"splitnum\0\x80\0");
+                        for (key, value) in [("hostname", "A Minecraft Server"), ("version", "1.21.1"), ("plugins", "Paper on 1.21.1: WorldEdit 7.3; LuckPerms 5.4"), ("numplayers", "2")] {
+                            response.extend(format!("{}\0{}\0", key, value).as_bytes());
+                        }
+                        response.push(0);
+                        response.extend(b"\x01player_\0\0Alex\0Steve\0\0");
+                    },
+                    _ => continue,
+                }
+                socket.send_to(&response, from).unwrap();
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn basic_stat() {
+        let client = QueryClient::connect("127.0.0.1", fake_server()).unwrap();
+        let stat = client.basic_stat().unwrap();
+        assert_eq!(stat.motd, "A Minecraft Server");
+        assert_eq!((stat.gametype.as_str(), stat.map.as_str()), ("SMP", "world"));
+        assert_eq!((stat.numplayers, stat.maxplayers), (2, 20));
+        assert_eq!((stat.hostip.as_str(), stat.hostport), ("127.0.0.1", 25565));
+    }
+
+    #[test]
+    fn full_stat() {
+        let client = QueryClient::connect("127.0.0.1", fake_server()).unwrap();
+        let stat = client.full_stat().unwrap();
+        assert_eq!(stat.info["version"], "1.21.1");
+        assert_eq!(stat.info["hostname"], "A Minecraft Server");
+        assert_eq!(stat.server_mod.as_deref(), Some("Paper on 1.21.1"));
+        assert_eq!(stat.plugins, ["WorldEdit 7.3", "LuckPerms 5.4"]);
+        assert_eq!(stat.players, ["Alex", "Steve"]);
+    }
+}
@@ -0,0 +1,244 @@
+use std::{error::Error, io::{self, Read, Write}, net::TcpStream, time::Duration};
+
+use crate::{ping, properties::Properties, server};
+
+const DEFAULT_PORT: u16 = 25575;
+// vanilla splits responses at 4096 bytes, other servers may not
+const MAX_PACKET_LEN: i32 = 1 << 20;
+
+const TYPE_RESPONSE: i32 = 0;
+const TYPE_COMMAND: i32 = 2;
+const TYPE_AUTH: i32 = 3;
+/// the server answers unknown packet types with a single response, which marks the end of a split response
+const TYPE_END_MARKER: i32 = 100;
+
+pub struct Packet {
+    pub id: i32,
+    pub r#type: i32,
+    pub body: String,
+}
+
+impl Packet {
+    pub fn encode(&self) -> Vec<u8> {
+        let body = self.body.as_bytes();
+        let mut data = Vec::with_capacity(14 + body.len());
+        data.extend(((body.len() + 10) as i32).to_le_bytes());
+        data.extend(self.id.to_le_bytes());
+        data.extend(self.r#type.to_le_bytes());
+        data.extend(body);
+        data.extend([0, 0]);
+        data
+    }
+
+    pub fn read(reader: &mut impl Read) -> Result<Packet, Box<dyn Error>> {
+        let mut int = [0u8; 4];
+        reader.read_exact(&mut int)?;
+        let len = i32::from_le_bytes(int);
+        if !(10..=MAX_PACKET_LEN).contains(&len) {
+            return Err(format!("Invalid rcon packet length {}", len).into());
+        }
+
+        let mut data = vec![0u8; len as usize];
+        reader.read_exact(&mut data)?;
+        let id = i32::from_le_bytes(data[0..4].try_into().unwrap());
+        let r#type = i32::from_le_bytes(data[4..8].try_into().unwrap());
+        let body = String::from_utf8_lossy(&data[8..data.len() - 2]).to_string();
+
+        Ok(Packet { id, r#type, body })
+    }
+}
+
+pub struct RconClient {
+    stream: TcpStream,
+    next_id: i32,
+}
+
+impl RconClient {
+    pub fn connect(address: &str) -> Result<RconClient, Box<dyn Error>> {
+        let (host, port) = ping::split_address(address)?;
+        let stream = TcpStream::connect((host, port.unwrap_or(DEFAULT_PORT)))?;
+        stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+        Ok(RconClient { stream, next_id: 1 })
+    }
+
+    fn send(&mut self, r#type: i32, body: &str) -> Result<i32, Box<dyn Error>> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.stream.write_all(&Packet { id, r#type, body: body.to_owned() }.encode())?;
+        Ok(id)
+    }
+
+    pub fn login(&mut self, password: &str) -> Result<(), Box<dyn Error>> {
+        let id = self.send(TYPE_AUTH, password)?;
+        loop {
+            let packet = Packet::read(&mut self.stream)?;
+            // some servers send an empty response value before the auth response
+            if packet.r#type == TYPE_RESPONSE {
+                continue;
+            }
+            if packet.id == -1 {
+                return Err("Wrong rcon password".into());
+            }
+            if packet.id == id {
+                return Ok(());
+            }
+        }
+    }
+
+    /// runs a command, joining responses that were split into multiple packets
+    pub fn command(&mut self, command: &str) -> Result<String, Box<dyn Error>> {
+        let id = self.send(TYPE_COMMAND, command)?;
+        let end_id = self.send(TYPE_END_MARKER, "")?;
+
+        let mut response = String::new();
+        loop {
+            let packet = Packet::read(&mut self.stream)?;
+            if packet.id == end_id {
+                return Ok(response);
+            }
+            if packet.id == id {
+                response.push_str(&packet.body);
+            }
+        }
+    }
+}
+
+/// removes `§` formatting codes
+pub fn strip_colors(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+pub fn handle(opt_address: Option<String>, command: Vec<String>, opt_password: Option<String>, opt_server: Option<String>) {
+    // without an address, talk to a server managed by mc_cli
+    let (address, password) = if let Some(address) = opt_address {
+        (address, opt_password)
+    } else {
+        let dir = server::server_dir(opt_server);
+        let props = Properties::load(&dir.join("server.properties"));
+        if props.get_or_default("enable-rcon") != "true" {
+            eprintln!("FATAL: rcon isn't enabled, turn it on with `mc_cli server config set enable-rcon true`.");
+            std::process::exit(-1);
+        }
+        // an IPv6 server-ip needs brackets to be told apart from the port
+        let host = match props.local_host() {
+            host if host.contains(':') => format!("[{}]", host),
+            host => host,
+        };
+        (format!("{}:{}", host, props.get_or_default("rcon.port")), opt_password.or(Some(props.get_or_default("rcon.password"))))
+    };
+
+    let Some(password) = password else {
+        eprintln!("FATAL: No rcon password given, pass one with --password.");
+        std::process::exit(-1);
+    };
+
+    let mut client = match RconClient::connect(&address) {
+        Ok(client) => client,
+        Err(err) => {
+            eprintln!("FATAL: Failed to connect to {}: {}", address, err);
+            std::process::exit(-1);
+        },
+    };
+    if let Err(err) = client.login(&password) {
+        eprintln!("FATAL: Failed to log in to {}: {}", address, err);
+        std::process::exit(-1);
+    }
+
+    if !command.is_empty() {
+        match client.command(&command.join(" ")) {
+            Ok(response) => println!("{}", strip_colors(&response)),
+            Err(err) => {
+                eprintln!("FATAL: {}", err);
+                std::process::exit(-1);
+            },
+        }
+        return;
+    }
+
+    println!("Connected to {}, type `exit` to leave", address);
+    loop {
+        print!("> ");
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "exit" || line == "quit" {
+            break;
+        }
+        match client.command(line) {
+            Ok(response) => println!("{}", strip_colors(&response)),
+            Err(err) => {
+                eprintln!("FATAL: {}", err);
+                std::process::exit(-1);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use super::*;
+
+    const PASSWORD: &str = "hunter2";
+
+    /// answers like a vanilla server: an empty response before the auth response, commands split into
+    /// several packets, and a single response to the unknown end marker type
+    fn fake_server(responses: &'static [&'static str]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            while let Ok(packet) = Packet::read(&mut stream) {
+                let replies = match packet.r#type {
+                    TYPE_AUTH => {
+                        let id = if packet.body == PASSWORD { packet.id } else { -1 };
+                        vec![Packet { id: packet.id, r#type: TYPE_RESPONSE, body: String::new() }, Packet { id, r#type: TYPE_COMMAND, body: String::new() }]
+                    },
+                    TYPE_COMMAND => responses.iter().map(|body| Packet { id: packet.id, r#type: TYPE_RESPONSE, body: (*body).to_owned() }).collect(),
+                    r#type => vec![Packet { id: packet.id, r#type: TYPE_RESPONSE, body: format!("Unknown request {:x}", r#type) }],
+                };
+                for reply in replies {
+                    stream.write_all(&reply.encode()).unwrap();
+                }
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn logs_in() {
+        let mut client = RconClient::connect(&fake_server(&[])).unwrap();
+        assert!(client.login(PASSWORD).is_ok());
+    }
+
+    #[test]
+    fn wrong_password_fails() {
+        let mut client = RconClient::connect(&fake_server(&[])).unwrap();
+        assert!(client.login("wrong").is_err());
+    }
+
+    #[test]
+    fn joins_split_response() {
+        let mut client = RconClient::connect(&fake_server(&["There are 2 of a max of 20 players online: ", "§aAlex, Steve"])).unwrap();
+        client.login(PASSWORD).unwrap();
+        let response = client.command("list").unwrap();
+        assert_eq!(response, "There are 2 of a max of 20 players online: §aAlex, Steve");
+        assert_eq!(strip_colors(&response), "There are 2 of a max of 20 players online: Alex, Steve");
+    }
+}
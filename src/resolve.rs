@@ -0,0 +1,236 @@
+use std::{collections::HashSet, error::Error, fs, path::{Path, PathBuf}};
+
+use directories::ProjectDirs;
+use serde_json::{Map, Value};
+
+use crate::{app::LaunchOptions, mem, overrides, rules, vanilla, version::{Library, VersionJson}};
+
+/// A version JSON with its whole `inheritsFrom` chain merged in.
+pub struct ResolvedVersion {
+    pub json: VersionJson,
+    /// version dirs, starting with the requested version and ending with the root (usually vanilla) one
+    pub chain: Vec<PathBuf>,
+}
+
+impl ResolvedVersion {
+    pub fn dir(&self) -> &Path {
+        self.chain.first().unwrap()
+    }
+
+    /// the mc_cli data dir the version is installed in
+    pub fn data_dir(&self) -> &Path {
+        self.dir().parent().unwrap().parent().unwrap()
+    }
+
+    pub fn root_dir(&self) -> &Path {
+        self.chain.last().unwrap()
+    }
+
+    /// the dir holding the client.jar, taken from the `jar` field if the version has one
+    pub fn jar_dir(&self) -> PathBuf {
+        match &self.json.jar {
+            Some(jar) => self.dir().parent().unwrap().join(jar),
+            None => self.root_dir().to_path_buf(),
+        }
+    }
+
+    /// the libraries of the version for this platform in the order they're listed, the client jar last.
+    /// The child's libraries come first, so when two versions of a library are listed the loader's wins.
+    pub fn classpath(&self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let mut seen = HashSet::new();
+        let mut classpath = vec![];
+        for library in self.json.libraries.iter().filter(|library| rules::library_allowed(library)) {
+            // natives-only libraries of older versions, they get extracted instead
+            let Some((path, _)) = library.artifact()? else { continue };
+
+            // the classifier is part of the key, so natives-* artifacts don't replace their library
+            if !seen.insert(library.coordinate()?.key()) {
+                continue;
+            }
+
+            let found = self.chain.iter().map(|dir| dir.join("libs").join(&path)).find(|found| found.is_file());
+            match found {
+                Some(found) => classpath.push(found),
+                None => return Err(format!("The library {} ({}) is missing, try reinstalling the version", library.name, path).into()),
+            }
+        }
+
+        let client = self.jar_dir().join("client.jar");
+        if !client.is_file() {
+            return Err(format!("{} is missing, try reinstalling the version", client.display()).into());
+        }
+        classpath.push(client);
+        Ok(classpath)
+    }
+}
+
+pub fn read_json(ver_dir: &Path) -> Result<Value, Box<dyn Error>> {
+    let text = fs::read_to_string(ver_dir.join("version.json"))?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+/// Merges a child version JSON onto its parent the way the official launcher does:
+/// libraries of the child come first, arguments of the child are appended to the parent's,
+/// and every other key set by the child replaces the parent's.
+pub fn merge(child: Value, parent: Value) -> Value {
+    let Value::Object(child) = child else { return parent };
+    let Value::Object(mut merged) = parent else { return Value::Object(child) };
+
+    if !merged.contains_key("jar") && let Some(id) = merged.get("id").cloned() {
+        merged.insert("jar".to_owned(), id);
+    }
+
+    for (key, value) in child {
+        match key.as_str() {
+            // the merged version inherits from whatever the parent inherits from
+            "inheritsFrom" => {},
+            "libraries" => {
+                let mut libraries = value.as_array().cloned().unwrap_or_default();
+                if let Some(Value::Array(parent_libraries)) = merged.remove("libraries") {
+                    libraries.extend(parent_libraries);
+                }
+                merged.insert(key, Value::Array(libraries));
+            },
+            "arguments" => {
+                let mut arguments = match merged.remove("arguments") {
+                    Some(Value::Object(arguments)) => arguments,
+                    _ => Map::new(),
+                };
+                if let Value::Object(child_arguments) = value {
+                    for (kind, args) in child_arguments {
+                        let mut all = match arguments.remove(&kind) {
+                            Some(Value::Array(args)) => args,
+                            _ => vec![],
+                        };
+                        all.extend(args.as_array().cloned().unwrap_or_default());
+                        arguments.insert(kind, Value::Array(all));
+                    }
+                }
+                merged.insert(key, Value::Object(arguments));
+            },
+            _ => {
+                merged.insert(key, value);
+            },
+        }
+    }
+
+    Value::Object(merged)
+}
+
+/// merges the JSONs of an `inheritsFrom` chain, given from the child to the root. It's merged from the root down,
+/// so each child is merged onto its fully resolved parent and gets the parent's `jar` unless it names its own
+pub fn merge_chain(jsons: Vec<Value>) -> Value {
+    let mut jsons = jsons.into_iter().rev();
+    let root = jsons.next().unwrap_or(Value::Null);
+    jsons.fold(root, |parent, child| merge(child, parent))
+}
+
+/// Resolves the version in `ver_dir`, following `inheritsFrom` through the sibling version dirs.
+pub fn resolve(ver_dir: &Path) -> Result<ResolvedVersion, Box<dyn Error>> {
+    let vers = ver_dir.parent().unwrap();
+    let mut chain = vec![ver_dir.to_path_buf()];
+    let mut jsons = vec![read_json(ver_dir)?];
+
+    while let Some(parent) = jsons.last().unwrap().get("inheritsFrom").and_then(Value::as_str).map(str::to_owned) {
+        let parent_dir = vers.join(&parent);
+        if chain.contains(&parent_dir) {
+            return Err(format!("Version {} inherits from itself", parent).into());
+        }
+        jsons.push(read_json(&parent_dir)?);
+        chain.push(parent_dir);
+    }
+    let merged = merge_chain(jsons);
+
+    let mut json = serde_path_to_error::deserialize::<_, VersionJson>(merged)
+        .map_err(|err| format!("Failed to parse merged version json at {}: {}", err.path(), err.inner()))?;
+    json.libraries = overrides::apply(&json.libraries);
+
+    Ok(ResolvedVersion { json, chain })
+}
+
+/// Installs a child version JSON (fabric/quilt profile, liteloader, forge, or one provided by the user)
+/// into `ver_dir`: its parent gets installed through the vanilla installer and its own libraries
+/// are downloaded next to it.
+pub async fn install(ver_dir: &Path, json_text: &str, limit: String) -> Result<(), Box<dyn Error>> {
+    let json: Value = serde_json::from_str(json_text)?;
+
+    let _ = fs::create_dir_all(ver_dir.join("libs"));
+
+    if let Some(parent) = json.get("inheritsFrom").and_then(Value::as_str)
+        && !ver_dir.parent().unwrap().join(parent).join("version.json").exists() {
+        vanilla::handle(Some(parent.to_owned()), limit, false, LaunchOptions::default()).await;
+    }
+
+    let libraries: Vec<Library> = serde_json::from_value(json.get("libraries").cloned().unwrap_or(Value::Array(vec![])))?;
+    vanilla::download_libraries(&libraries, &ver_dir.join("libs")).await?;
+
+    // written last, so a version whose libraries failed to download isn't taken as installed
+    fs::write(ver_dir.join("version.json"), json_text)?;
+    Ok(())
+}
+
+pub async fn handle(json_path: PathBuf, limit: String, options: LaunchOptions) {
+    mem::check_if_valid(limit.clone());
+
+    let text = fs::read_to_string(&json_path).expect("Failed to read version json");
+    let json: Value = serde_json::from_str(&text).expect("Failed to parse version json");
+    let id = json.get("id").and_then(Value::as_str).expect("The version json has no id");
+
+    let proj_dirs = ProjectDirs::from("me", "illia", "mc_cli").unwrap();
+    let ver = proj_dirs.data_dir().join("vers").join(id);
+
+    if let Err(err) = install(&ver, &text, limit.clone()).await {
+        eprintln!("FATAL: Failed to install {}:\n{}", id, err);
+        std::process::exit(-1);
+    }
+
+    println!("Launching {} with memory limit {}", id, limit);
+    let resolved = resolve(&ver).expect("Failed to resolve version");
+    vanilla::download_logging_config(&resolved.json, &resolved.data_dir().join("assets")).await;
+    vanilla::launch(resolved, limit, options);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn three_level_chain_uses_the_root_jar() {
+        let custom = json!({
+            "id": "custom",
+            "inheritsFrom": "fabric-1.21",
+            "libraries": [{ "name": "com.example:mod:1" }],
+            "arguments": { "jvm": ["-Dcustom=1"] },
+        });
+        let fabric = json!({
+            "id": "fabric-1.21",
+            "inheritsFrom": "1.21",
+            "mainClass": "net.fabricmc.loader.impl.launch.knot.KnotClient",
+            "libraries": [{ "name": "net.fabricmc:fabric-loader:0.16.0" }],
+            "arguments": { "jvm": ["-DFabricMcEmu= net.minecraft.client.main.Main "] },
+        });
+        let vanilla = json!({
+            "id": "1.21",
+            "mainClass": "net.minecraft.client.main.Main",
+            "libraries": [{ "name": "org.lwjgl:lwjgl:3.3.3" }],
+            "arguments": { "jvm": ["-Xss1M"] },
+        });
+
+        let merged = merge_chain(vec![custom, fabric, vanilla]);
+        assert_eq!(merged["jar"], "1.21");
+        assert_eq!(merged["id"], "custom");
+        assert_eq!(merged["mainClass"], "net.fabricmc.loader.impl.launch.knot.KnotClient");
+        let libraries = merged["libraries"].as_array().unwrap().iter().map(|library| library["name"].as_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(libraries, ["com.example:mod:1", "net.fabricmc:fabric-loader:0.16.0", "org.lwjgl:lwjgl:3.3.3"]);
+        assert_eq!(merged["arguments"]["jvm"], json!(["-Xss1M", "-DFabricMcEmu= net.minecraft.client.main.Main ", "-Dcustom=1"]));
+    }
+
+    #[test]
+    fn child_keeps_its_own_jar() {
+        let forge = json!({ "id": "forge", "inheritsFrom": "1.12.2", "jar": "1.12.2-patched" });
+        let vanilla = json!({ "id": "1.12.2" });
+        assert_eq!(merge_chain(vec![forge, vanilla])["jar"], "1.12.2-patched");
+    }
+}
@@ -1,46 +1,76 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::OnceLock};
 
-use crate::version::{LibraryClassifiers, LibraryDownload, Rule};
+use regex::Regex;
 
-pub fn matches_os_rule(rule: &Rule) -> bool {
-    let mut matches_rule: bool = false;
-    let os = &rule.os;
-    if os.is_none() {
-        return true;
+use crate::version::{Library, LibraryClassifiers, LibraryDownload, Os, Rule};
+
+/// The OS the rules get evaluated against
+pub struct Platform {
+    pub name: &'static str,
+    pub arch: &'static str,
+    pub version: String,
+}
+
+impl Platform {
+    pub fn current() -> &'static Platform {
+        static CURRENT: OnceLock<Platform> = OnceLock::new();
+        CURRENT.get_or_init(|| Platform {
+            name: rust_os_to_minecraft_os(),
+            arch: rust_arch_to_minecraft_arch(),
+            version: os_version(),
+        })
     }
-    match os.clone().unwrap().clone() {
-        crate::version::Os::Name { name } => {
-            let os = rust_os_to_minecraft_os();
-            if name == os {
-                matches_rule = true;
-            }
-        },
-        crate::version::Os::Arch { arch } => {
-            let cur_arch = rust_arch_to_minecraft_arch();
-            if arch == cur_arch {
-                matches_rule = true;
-            }
-        },
-        crate::version::Os::Both { name, arch } => {
-            let cur_arch = rust_arch_to_minecraft_arch();
-            if arch == cur_arch {
-                matches_rule = true;
-            }
-            let os = rust_os_to_minecraft_os();
-            if name == os && matches_rule {
-                matches_rule = true;
-            }
-        },
+}
+
+/// the version java reports as `os.version`, which is what the `os.version` regexes are written for
+fn os_version() -> String {
+    if std::env::consts::OS == "macos" {
+        // the kernel version sys-info reports on macOS isn't the product version
+        if let Ok(output) = std::process::Command::new("sw_vers").arg("-productVersion").output() {
+            return String::from_utf8_lossy(&output.stdout).trim().to_owned();
+        }
     }
-    return matches_rule && rule.action == "allow";
+    sys_info::os_release().unwrap_or_default()
+}
+
+fn os_matches(os: &Os, platform: &Platform) -> bool {
+    let name_matches = os.name.as_ref().is_none_or(|name| name == platform.name);
+    let arch_matches = os.arch.as_ref().is_none_or(|arch| arch == platform.arch);
+    // an invalid regex matches nothing, like in the official launcher
+    let version_matches = os.version.as_ref().is_none_or(|version| {
+        Regex::new(version).is_ok_and(|regex| regex.is_match(&platform.version))
+    });
+    name_matches && arch_matches && version_matches
+}
+
+/// whether every condition of the rule holds, features missing from `features` count as off
+pub fn rule_applies(rule: &Rule, features: &HashMap<String, bool>, platform: &Platform) -> bool {
+    let os_matches = rule.os.as_ref().is_none_or(|os| os_matches(os, platform));
+    let features_match = rule.features.as_ref().is_none_or(|required| {
+        required.0.iter().all(|(name, value)| features.get(name).copied().unwrap_or(false) == *value)
+    });
+    os_matches && features_match
 }
 
-pub fn matches_arg_rule(features: HashMap<String, bool>, rule: &Rule) -> bool {
-    if rule.features.is_none() {
+/// Evaluates rules the way the official launcher does: they're checked in order and the last one
+/// that applies decides, with nothing allowed unless a rule allows it. No rules allow everything.
+pub fn allowed_on(rules: &[Rule], features: &HashMap<String, bool>, platform: &Platform) -> bool {
+    if rules.is_empty() {
         return true;
     }
-    let matches_rule: bool = hashmap_contains::<String, bool>(&features, &rule.features.clone().unwrap().0);
-    return matches_rule && rule.action == "allow";
+    rules
+        .iter()
+        .rfind(|rule| rule_applies(rule, features, platform))
+        .is_some_and(|rule| rule.action == "allow")
+}
+
+pub fn allowed(rules: &[Rule], features: &HashMap<String, bool>) -> bool {
+    allowed_on(rules, features, Platform::current())
+}
+
+/// libraries only have OS rules
+pub fn library_allowed(library: &Library) -> bool {
+    library.rules.as_deref().is_none_or(|rules| allowed(rules, &HashMap::new()))
 }
 
 pub fn classifiers_needed(classifiers: &LibraryClassifiers) -> Vec<&LibraryDownload> {
@@ -65,10 +95,8 @@ pub fn classifiers_needed(classifiers: &LibraryClassifiers) -> Vec<&LibraryDownl
                 if let Some(download) = &classifiers.natives_windows_64 {
                     downloads.push(download);
                 }
-            } else if arch == "x86" {
-                if let Some(download) = &classifiers.natives_windows_32 {
-                    downloads.push(download);
-                }
+            } else if arch == "x86" && let Some(download) = &classifiers.natives_windows_32 {
+                downloads.push(download);
             }
 
             if let Some(download) = &classifiers.natives_windows {
@@ -80,10 +108,8 @@ pub fn classifiers_needed(classifiers: &LibraryClassifiers) -> Vec<&LibraryDownl
                 if let Some(download) = &classifiers.natives_linux_64 {
                     downloads.push(download);
                 }
-            } else if arch == "x86" {
-                if let Some(download) = &classifiers.natives_linux_32 {
-                    downloads.push(download);
-                }
+            } else if arch == "x86" && let Some(download) = &classifiers.natives_linux_32 {
+                downloads.push(download);
             }
 
             if let Some(download) = &classifiers.natives_linux {
@@ -96,13 +122,6 @@ pub fn classifiers_needed(classifiers: &LibraryClassifiers) -> Vec<&LibraryDownl
     downloads
 }
 
-pub fn hashmap_contains<K: Eq + std::hash::Hash, V: PartialEq>(
-    big: &std::collections::HashMap<K, V>,
-    small: &std::collections::HashMap<K, V>,
-) -> bool {
-    small.iter().all(|(k, v)| big.get(k) == Some(v))
-}
-
 pub fn rust_os_to_minecraft_os() -> &'static str {
     match std::env::consts::OS {
         "windows" => "windows",
@@ -117,6 +136,70 @@ pub fn rust_arch_to_minecraft_arch() -> &'static str {
         "x86" => "x86",
         "aarch64" => "aarch64",
         "arm" => "arm32",
+        "riscv64" => "riscv64",
         _ => "unknown",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// what's checked, the rules as they are in the JSON, the features and platform, and whether they allow it
+    type Case<'a> = (&'a str, &'a str, &'a HashMap<String, bool>, &'a Platform, bool);
+
+    fn platform(name: &'static str, arch: &'static str, version: &str) -> Platform {
+        Platform { name, arch, version: version.to_owned() }
+    }
+
+    #[test]
+    fn rules_from_version_jsons() {
+        let windows_10 = platform("windows", "x86_64", "10.0");
+        let windows_7 = platform("windows", "x86_64", "6.1");
+        let windows_32 = platform("windows", "x86", "10.0");
+        let linux = platform("linux", "x86_64", "6.8.0");
+        let osx = platform("osx", "aarch64", "14.5");
+        let osx_leopard = platform("osx", "x86_64", "10.5.8");
+
+        let demo = HashMap::from([("is_demo_user".to_owned(), true)]);
+        let resolution = HashMap::from([("has_custom_resolution".to_owned(), true)]);
+        let none = HashMap::new();
+
+        let cases: &[Case] = &[
+            // 1.12.2, org.lwjgl.lwjgl:lwjgl:2.9.4-nightly-20150209
+            ("lwjgl on linux", r#"[{"action": "allow"}, {"action": "disallow", "os": {"name": "osx"}}]"#, &none, &linux, true),
+            ("lwjgl on windows", r#"[{"action": "allow"}, {"action": "disallow", "os": {"name": "osx"}}]"#, &none, &windows_10, true),
+            ("lwjgl on osx", r#"[{"action": "allow"}, {"action": "disallow", "os": {"name": "osx"}}]"#, &none, &osx, false),
+            // 1.12.2, org.lwjgl.lwjgl:lwjgl:2.9.2-nightly-20140822
+            ("osx lwjgl on osx", r#"[{"action": "allow", "os": {"name": "osx"}}]"#, &none, &osx, true),
+            ("osx lwjgl on linux", r#"[{"action": "allow", "os": {"name": "osx"}}]"#, &none, &linux, false),
+            // 1.13+ jvm arguments, -Dos.name=Windows 10 -Dos.version=10.0
+            ("windows 10 args on windows 10", r#"[{"action": "allow", "os": {"name": "windows", "version": "^10\\."}}]"#, &none, &windows_10, true),
+            ("windows 10 args on windows 7", r#"[{"action": "allow", "os": {"name": "windows", "version": "^10\\."}}]"#, &none, &windows_7, false),
+            ("windows 10 args on linux", r#"[{"action": "allow", "os": {"name": "windows", "version": "^10\\."}}]"#, &none, &linux, false),
+            // 1.13+ jvm arguments, -Xss1M
+            ("-Xss1M on x86", r#"[{"action": "allow", "os": {"arch": "x86"}}]"#, &none, &windows_32, true),
+            ("-Xss1M on x86_64", r#"[{"action": "allow", "os": {"arch": "x86"}}]"#, &none, &windows_10, false),
+            // 1.13+ game arguments, --demo
+            ("--demo for a demo user", r#"[{"action": "allow", "features": {"is_demo_user": true}}]"#, &demo, &linux, true),
+            ("--demo for a full user", r#"[{"action": "allow", "features": {"is_demo_user": true}}]"#, &none, &linux, false),
+            ("--demo with other features", r#"[{"action": "allow", "features": {"is_demo_user": true}}]"#, &resolution, &linux, false),
+            // 1.13+ game arguments, --width ${resolution_width} --height ${resolution_height}
+            ("--width with a resolution", r#"[{"action": "allow", "features": {"has_custom_resolution": true}}]"#, &resolution, &linux, true),
+            ("--width without one", r#"[{"action": "allow", "features": {"has_custom_resolution": true}}]"#, &none, &linux, false),
+            // most libraries have no rules
+            ("no rules", "[]", &none, &osx, true),
+            // 1.5.2, org.lwjgl.lwjgl:lwjgl:2.9.0, leopard gets an older lwjgl
+            ("lwjgl 2.9.0 on leopard", r#"[{"action": "allow"}, {"action": "disallow", "os": {"name": "osx", "version": "^10\\.5\\.\\d$"}}]"#, &none, &osx_leopard, false),
+            ("lwjgl 2.9.0 on sonoma", r#"[{"action": "allow"}, {"action": "disallow", "os": {"name": "osx", "version": "^10\\.5\\.\\d$"}}]"#, &none, &osx, true),
+            // the last rule that applies wins
+            ("disallow then allow", r#"[{"action": "disallow", "os": {"name": "osx"}}, {"action": "allow"}]"#, &none, &osx, true),
+            ("only disallow", r#"[{"action": "disallow", "os": {"name": "osx"}}]"#, &none, &linux, false),
+        ];
+
+        for (name, rules, features, platform, expected) in cases {
+            let rules: Vec<Rule> = serde_json::from_str(rules).unwrap();
+            assert_eq!(allowed_on(&rules, features, platform), *expected, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::{fs::{self, File}, io::Read as _, path::PathBuf, process::Stdio, thread, time::Duration};
+
+use chrono::{DateTime, Local};
+use cli_table::{Cell as _, Table as _};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::{app::LogLevel, log4j, logs, process, vanilla::LaunchCommand};
+
+const KILL_TIMEOUT: Duration = Duration::from_secs(10);
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A detached game, saved as running/<instance>.json in the data dir
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RunState {
+    pub pid: u32,
+    pub instance: String,
+    pub account: String,
+    pub started: DateTime<Local>,
+    pub log: PathBuf,
+}
+
+fn data_dir() -> PathBuf {
+    let proj_dirs = ProjectDirs::from("me", "illia", "mc_cli").unwrap();
+    proj_dirs.data_dir().to_path_buf()
+}
+
+fn state_path(instance: &str) -> PathBuf {
+    data_dir().join("running").join(format!("{}.json", instance))
+}
+
+/// where the output of a launch of the instance started now goes, older logs get rotated to make room for it
+pub fn new_log_path(instance: &str) -> PathBuf {
+    let dir = data_dir().join("launches").join(instance);
+    let _ = fs::create_dir_all(&dir);
+    logs::rotate(&dir);
+    dir.join(format!("{}.log", Local::now().format("%Y-%m-%d_%H-%M-%S")))
+}
+
+pub fn read_state(instance: &str) -> Option<RunState> {
+    let text = fs::read_to_string(state_path(instance)).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+pub fn states() -> Vec<RunState> {
+    let mut states = fs::read_dir(data_dir().join("running"))
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| serde_json::from_str::<RunState>(&fs::read_to_string(e.path()).ok()?).ok())
+        .collect::<Vec<_>>();
+    states.sort_by_key(|state| state.started);
+    states
+}
+
+pub fn check_not_running(instance: &str) {
+    if let Some(state) = read_state(instance) && process::is_alive(state.pid) {
+        eprintln!("FATAL: {} is already running (pid {}), stop it with `mc_cli kill {}`.", instance, state.pid, instance);
+        std::process::exit(-1);
+    }
+}
+
+/// starts the game in its own session with its output going to a log file, and returns right away
+pub fn spawn_detached(instance: &str, account: &str, launch_command: &LaunchCommand, log_path: PathBuf) {
+    let log = File::create(&log_path).expect("Failed to create launch log");
+
+    let mut command = launch_command.to_command();
+    command
+        .stdin(Stdio::null())
+        .stdout(log.try_clone().expect("Failed to open launch log"))
+        .stderr(log);
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt as _;
+        // a new session, so closing the terminal doesn't take the game with it
+        unsafe {
+            command.pre_exec(|| {
+                libc::setsid();
+                Ok(())
+            });
+        }
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt as _;
+        const DETACHED_PROCESS: u32 = 0x00000008;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+        command.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+    }
+    // never waited on, mc_cli exits right away and the game outlives it
+    #[allow(clippy::zombie_processes)]
+    let process = command.spawn().expect("Failed to run Minecraft");
+
+    let state = RunState {
+        pid: process.id(),
+        instance: instance.to_owned(),
+        account: account.to_owned(),
+        started: Local::now(),
+        log: log_path.clone(),
+    };
+    let path = state_path(instance);
+    let _ = fs::create_dir_all(path.parent().unwrap());
+    fs::write(&path, serde_json::to_string_pretty(&state).unwrap()).expect("Failed to write run state");
+
+    println!("Started {} in the background (pid {})", instance, state.pid);
+    println!("Log: {}", log_path.display());
+}
+
+pub fn ps() {
+    let mut rows = vec![
+        vec![
+            "INSTANCE".cell(),
+            "PID".cell(),
+            "ACCOUNT".cell(),
+            "STARTED".cell(),
+            "STATUS".cell(),
+        ]
+    ];
+
+    for state in states() {
+        let alive = process::is_alive(state.pid);
+        rows.push(vec![
+            state.instance.clone().cell(),
+            state.pid.cell(),
+            state.account.clone().cell(),
+            state.started.format("%Y-%m-%d %H:%M:%S").to_string().cell(),
+            if alive { "running" } else { "exited" }.cell(),
+        ]);
+        // exited games are shown once and then forgotten
+        if !alive {
+            let _ = fs::remove_file(state_path(&state.instance));
+        }
+    }
+
+    let table = rows.table();
+    println!("{}", table.display().unwrap());
+}
+
+/// the log of the last launch of the instance, which is gzipped once it gets rotated
+fn latest_log(instance: &str) -> Option<PathBuf> {
+    if let Some(state) = read_state(instance)
+        && state.log.is_file() {
+        return Some(state.log);
+    }
+    logs::log_files(&data_dir().join("launches").join(instance)).pop()
+}
+
+pub fn logs(instance: String, follow: bool, level: LogLevel) {
+    let Some(path) = latest_log(&instance) else {
+        eprintln!("FATAL: {} hasn't been launched in the background yet.", instance);
+        std::process::exit(-1);
+    };
+    let mut printer = log4j::Printer::new(level, None);
+
+    // a rotated log is finished, there's nothing to follow
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        let text = logs::read_log(&path).expect("Failed to read launch log");
+        for line in text.lines() {
+            printer.push(line);
+        }
+        printer.finish();
+        return;
+    }
+
+    let mut file = File::open(&path).expect("Failed to open launch log");
+    // kept as bytes until the line is whole, a read can end in the middle of a character
+    let mut partial = vec![];
+
+    loop {
+        let _ = file.read_to_end(&mut partial);
+        // only whole lines, the rest waits for the game to finish writing it
+        while let Some(end) = partial.iter().position(|&byte| byte == b'\n') {
+            let line = String::from_utf8_lossy(&partial[..end]).trim_end_matches('\r').to_owned();
+            partial.drain(..=end);
+            printer.push(&line);
+        }
+
+        let running = read_state(&instance).is_some_and(|state| state.log == path && process::is_alive(state.pid));
+        if !follow || !running {
+            if !partial.is_empty() {
+                printer.push(&String::from_utf8_lossy(&partial));
+            }
+            printer.finish();
+            return;
+        }
+        thread::sleep(FOLLOW_INTERVAL);
+    }
+}
+
+pub fn kill(instance: String) {
+    let Some(state) = read_state(&instance) else {
+        eprintln!("FATAL: {} isn't running in the background.", instance);
+        std::process::exit(-1);
+    };
+
+    if process::is_alive(state.pid) {
+        println!("Stopping {} (pid {})", instance, state.pid);
+        process::stop(state.pid, KILL_TIMEOUT);
+    } else {
+        println!("{} already exited", instance);
+    }
+    let _ = fs::remove_file(state_path(&instance));
+}
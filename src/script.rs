@@ -0,0 +1,127 @@
+use std::{fs, path::PathBuf};
+
+use crate::{app::{LaunchOptions, ScriptFormat}, assets, instance, natives, resolve, vanilla::{self, LaunchCommand}};
+
+/// quotes an argument for POSIX sh, leaving it as it is when that's safe
+pub fn sh_quote(arg: &str) -> String {
+    let safe = !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c));
+    if safe {
+        return arg.to_owned();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// quotes an argument for a batch file, the way the program's C runtime parses it back
+pub fn bat_quote(arg: &str) -> String {
+    // variables get expanded even inside quotes
+    let arg = arg.replace('%', "%%");
+    let safe = !arg.is_empty() && !arg.chars().any(|c| c.is_whitespace() || "\"&|<>^(),;=!".contains(c));
+    if safe {
+        return arg;
+    }
+
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            // backslashes only escape when they come before a quote, which has to be escaped too
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            },
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            },
+        }
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+pub fn render(command: &LaunchCommand, format: ScriptFormat, title: &str) -> String {
+    let mut script = String::new();
+    match format {
+        ScriptFormat::Sh => {
+            script.push_str("#!/bin/sh\n");
+            script.push_str(&format!("# {}, exported by mc_cli {}\n", title, env!("CARGO_PKG_VERSION")));
+            script.push_str(&format!("cd {} || exit 1\n", sh_quote(&command.current_dir.to_string_lossy())));
+            for (key, value) in &command.env {
+                script.push_str(&format!("export {}={}\n", key, sh_quote(value)));
+            }
+            script.push_str(&format!("exec {}", sh_quote(&command.program)));
+            for arg in &command.args {
+                script.push_str(&format!(" \\\n  {}", sh_quote(arg)));
+            }
+            script.push_str(" \\\n  \"$@\"\n");
+        },
+        ScriptFormat::Bat => {
+            script.push_str("@echo off\r\n");
+            script.push_str(&format!("rem {}, exported by mc_cli {}\r\n", title, env!("CARGO_PKG_VERSION")));
+            script.push_str(&format!("cd /d {}\r\n", bat_quote(&command.current_dir.to_string_lossy())));
+            for (key, value) in &command.env {
+                script.push_str(&format!("set \"{}={}\"\r\n", key, value.replace('%', "%%")));
+            }
+            script.push_str(&bat_quote(&command.program));
+            for arg in &command.args {
+                script.push_str(&format!(" ^\r\n  {}", bat_quote(arg)));
+            }
+            script.push_str(" ^\r\n  %*\r\n");
+        },
+    }
+    script
+}
+
+/// prints what a launch would run without running it
+pub fn print_dry_run(command: &LaunchCommand) {
+    println!("Working dir: {}", command.current_dir.display());
+    if !command.env.is_empty() {
+        println!("Environment:");
+        for (key, value) in &command.env {
+            println!("  {}={}", key, sh_quote(value));
+        }
+    }
+    println!("Command:");
+    println!("{}", command.full_args().iter().map(|arg| sh_quote(arg)).collect::<Vec<_>>().join(" "));
+}
+
+pub fn export(name: String, opt_output: Option<PathBuf>, opt_format: Option<ScriptFormat>, limit: String, options: LaunchOptions) {
+    let format = opt_format.unwrap_or(match &opt_output {
+        Some(output) if output.extension().is_some_and(|ext| ext == "bat" || ext == "cmd") => ScriptFormat::Bat,
+        Some(_) => ScriptFormat::Sh,
+        None if cfg!(target_os = "windows") => ScriptFormat::Bat,
+        None => ScriptFormat::Sh,
+    });
+    let output = opt_output.unwrap_or(PathBuf::from(match format {
+        ScriptFormat::Sh => format!("{}.sh", name),
+        ScriptFormat::Bat => format!("{}.bat", name),
+    }));
+
+    let resolved = resolve::resolve(&instance::instance_dir(&name)).expect("Failed to resolve version");
+    let options = instance::load(resolved.dir()).apply(options);
+    let separator = match format {
+        ScriptFormat::Sh => ":",
+        ScriptFormat::Bat => ";",
+    };
+    let command = vanilla::build_command(&resolved, &limit, &options, separator);
+    // the script runs java directly, so the natives and assets it points at have to be there already
+    if let Err(err) = natives::extract(&resolved) {
+        eprintln!("FATAL: Failed to extract natives: {}", err);
+        std::process::exit(-1);
+    }
+    if let Err(err) = assets::lay_out_legacy(&resolved.data_dir().join("assets"), &resolved.json.assetIndex.id, &command.current_dir) {
+        eprintln!("Warning: Failed to copy the assets to where this version looks for them: {}", err);
+    }
+
+    fs::write(&output, render(&command, format, &format!("Minecraft {}", name))).expect("Failed to write launch script");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+        let _ = fs::set_permissions(&output, fs::Permissions::from_mode(0o755));
+    }
+    println!("Wrote {}", output.display());
+}
@@ -0,0 +1,291 @@
+use std::{fs::{self, File, OpenOptions}, io::{self, Write as _}, path::{Path, PathBuf}, process::Stdio, sync::{Arc, Mutex}, time::Duration};
+
+use chrono::Local;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use tokio::{io::{AsyncBufReadExt as _, AsyncRead, AsyncWriteExt as _, BufReader}, sync::mpsc};
+
+use crate::{app::ServerLoader, fabric, mem, process, util, vanilla, version::{UseQuilt, VersionJson}};
+
+const EULA_URL: &str = "https://aka.ms/MinecraftEULA";
+const CONFIG_FILE: &str = "mc_cli.json";
+const PID_FILE: &str = "mc_cli.pid";
+const CONSOLE_LOG: &str = "mc_cli-console.log";
+const STOP_TIMEOUT: Duration = Duration::from_secs(60);
+const TERMINATE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How to start an installed server, saved as mc_cli.json in the server dir
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ServerConfig {
+    pub version: String,
+    pub loader: Option<String>,
+    /// the server jar gets run with `-jar` when there's no main class
+    pub main_class: Option<String>,
+    /// relative to the server dir
+    pub classpath: Vec<String>,
+    pub jvm_args: Vec<String>,
+}
+
+pub fn servers_dir() -> PathBuf {
+    let proj_dirs = ProjectDirs::from("me", "illia", "mc_cli").unwrap();
+    proj_dirs.data_dir().join("servers")
+}
+
+/// returns the dir of the server called `opt_name`, or of the only installed server if there's no name
+pub fn server_dir(opt_name: Option<String>) -> PathBuf {
+    let servers = servers_dir();
+    if let Some(name) = opt_name {
+        let dir = servers.join(&name);
+        if !dir.join(CONFIG_FILE).is_file() {
+            eprintln!("FATAL: There's no server called {}.", name);
+            std::process::exit(-1);
+        }
+        return dir;
+    }
+
+    let installed = servers
+        .read_dir()
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.join(CONFIG_FILE).is_file())
+        .collect::<Vec<_>>();
+
+    match installed.len() {
+        0 => {
+            eprintln!("FATAL: No servers are installed. Install one with `mc_cli server install <version>`.");
+            std::process::exit(-1);
+        },
+        1 => installed[0].clone(),
+        _ => {
+            let names = installed.iter().map(|path| path.file_name().unwrap().to_string_lossy()).collect::<Vec<_>>();
+            eprintln!("FATAL: Multiple servers are installed, pick one with --name: {}", names.join(", "));
+            std::process::exit(-1);
+        },
+    }
+}
+
+pub fn read_config(server_dir: &Path) -> ServerConfig {
+    let text = fs::read_to_string(server_dir.join(CONFIG_FILE)).expect("Failed to read server config");
+    serde_json::from_str(&text).expect("Failed to parse server config")
+}
+
+/// makes sure eula.txt is agreed to, asking the user unless `accept` is set
+pub fn check_eula(server_dir: &Path, accept: bool) {
+    let eula = server_dir.join("eula.txt");
+    let accepted = fs::read_to_string(&eula)
+        .map(|text| text.lines().any(|line| line.trim() == "eula=true"))
+        .unwrap_or(false);
+    if accepted {
+        return;
+    }
+
+    if !accept {
+        print!("Do you agree to the Minecraft EULA ({})? [y/N] ", EULA_URL);
+        let _ = io::stdout().flush();
+        let mut answer = String::new();
+        let _ = io::stdin().read_line(&mut answer);
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            eprintln!("FATAL: You need to agree to the EULA in order to run the server.");
+            std::process::exit(-1);
+        }
+    }
+
+    fs::write(eula, format!("#By changing the setting below to TRUE you are indicating your agreement to our EULA ({}).\neula=true\n", EULA_URL))
+        .expect("Failed to write eula.txt");
+}
+
+pub async fn install(version: String, loader: Option<ServerLoader>, loader_version: Option<String>, opt_name: Option<String>, accept_eula: bool) {
+    let name = opt_name.unwrap_or(match loader {
+        Some(ServerLoader::Fabric) => format!("fabric-{}", version),
+        Some(ServerLoader::Quilt) => format!("quilt-{}", version),
+        None => version.clone(),
+    });
+    let dir = servers_dir().join(&name);
+    let _ = fs::create_dir_all(&dir);
+
+    let manifest = vanilla::get_manifest().await;
+    let ver_url = vanilla::get_ver_json_url(manifest, version.clone());
+    let text = util::download_text_no_save_async(&ver_url, "Downloaded version.json".to_owned()).await.expect("Failed to download version json");
+    let version_json: VersionJson = serde_json::from_str(&text).expect("Failed to parse version json");
+
+    let Some(server) = version_json.downloads.server else {
+        eprintln!("FATAL: Minecraft {} has no dedicated server.", version);
+        std::process::exit(-1);
+    };
+    let _ = util::download_async(&server.url, &dir.join("server.jar"), "Downloaded server jar".to_owned()).await.expect("Failed to download server jar");
+
+    let config = match loader {
+        None => ServerConfig {
+            version: version.clone(),
+            loader: None,
+            main_class: None,
+            classpath: vec![],
+            jvm_args: vec![],
+        },
+        Some(loader) => {
+            let (use_quilt, game_jar_property) = match loader {
+                ServerLoader::Fabric => (UseQuilt::No, "fabric.gameJarPath"),
+                ServerLoader::Quilt => (UseQuilt::Yes(true), "loader.gameJarPath"),
+            };
+            let (main_class, classpath) = fabric::install_server(&version, loader_version, &dir, use_quilt).await.unwrap_or_else(|err| {
+                eprintln!("FATAL: Failed to install the {:?} server: {}", loader, err);
+                std::process::exit(-1);
+            });
+            ServerConfig {
+                version: version.clone(),
+                loader: Some(format!("{:?}", loader).to_lowercase()),
+                main_class: Some(main_class),
+                classpath,
+                jvm_args: vec![format!("-D{}=server.jar", game_jar_property)],
+            }
+        },
+    };
+
+    fs::write(dir.join(CONFIG_FILE), serde_json::to_string_pretty(&config).unwrap()).expect("Failed to write server config");
+
+    check_eula(&dir, accept_eula);
+
+    println!("Installed server {} in {}", name, dir.display());
+}
+
+pub async fn start(opt_name: Option<String>, limit: String, accept_eula: bool) {
+    mem::check_if_valid(limit.clone());
+
+    let dir = server_dir(opt_name);
+    let config = read_config(&dir);
+    check_eula(&dir, accept_eula);
+
+    let mut cmd: Vec<String> = vec![format!("-Xmx{}", limit)];
+    cmd.extend(config.jvm_args);
+    match config.main_class {
+        Some(main_class) => {
+            let sep = if cfg!(target_os = "windows") { ";" } else { ":" };
+            cmd.push("-cp".to_owned());
+            cmd.push(config.classpath.join(sep));
+            cmd.push(main_class);
+        },
+        None => {
+            cmd.push("-jar".to_owned());
+            cmd.push("server.jar".to_owned());
+        },
+    }
+    cmd.push("nogui".to_owned());
+
+    println!("Starting server {} with memory limit {}", dir.file_name().unwrap().to_string_lossy(), limit);
+    println!("cmd: {:?}", cmd);
+
+    let mut command = tokio::process::Command::new("java");
+    command
+        .current_dir(&dir)
+        .args(&cmd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // keep Ctrl-C away from the server so it can be stopped with `stop` instead
+    #[cfg(unix)]
+    command.process_group(0);
+    let mut process = command.spawn().expect("Failed to run server");
+    let pid = process.id().unwrap_or_default();
+    let _ = fs::write(dir.join(PID_FILE), pid.to_string());
+
+    let _ = fs::create_dir_all(dir.join("logs"));
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join("logs").join(CONSOLE_LOG))
+        .expect("Failed to open console log");
+    let log = Arc::new(Mutex::new(log));
+
+    let stdout = process.stdout.take().expect("Failed to take stdout");
+    let stderr = process.stderr.take().expect("Failed to take stderr");
+    let tee_tasks = vec![
+        tokio::spawn(tee(stdout, Arc::clone(&log), false)),
+        tokio::spawn(tee(stderr, Arc::clone(&log), true)),
+    ];
+
+    // typed commands and the `stop` sent on Ctrl-C both go through here
+    let (commands, mut commands_rx) = mpsc::channel::<String>(16);
+    let mut child_stdin = process.stdin.take().expect("Failed to take stdin");
+    tokio::spawn(async move {
+        while let Some(command) = commands_rx.recv().await {
+            if child_stdin.write_all(format!("{}\n", command).as_bytes()).await.is_err() {
+                break;
+            }
+            let _ = child_stdin.flush().await;
+        }
+    });
+
+    // a plain thread, since a blocking stdin read would keep the runtime from shutting down
+    let stdin_commands = commands.clone();
+    std::thread::spawn(move || {
+        for line in io::stdin().lines() {
+            let Ok(line) = line else { break };
+            if stdin_commands.blocking_send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let status = tokio::select! {
+        status = process.wait() => status,
+        _ = tokio::signal::ctrl_c() => {
+            println!("Stopping the server, waiting up to {}s for the world to save...", STOP_TIMEOUT.as_secs());
+            let _ = commands.send("stop".to_owned()).await;
+
+            match tokio::time::timeout(STOP_TIMEOUT, process.wait()).await {
+                Ok(status) => status,
+                Err(_) => {
+                    eprintln!("The server didn't stop in time, sending SIGTERM");
+                    process::terminate(pid);
+                    match tokio::time::timeout(TERMINATE_TIMEOUT, process.wait()).await {
+                        Ok(status) => status,
+                        Err(_) => {
+                            eprintln!("The server still didn't stop, killing it");
+                            let _ = process.kill().await;
+                            process.wait().await
+                        },
+                    }
+                },
+            }
+        },
+    }.expect("Failed to wait for child");
+
+    futures_util::future::join_all(tee_tasks).await;
+    let _ = fs::remove_file(dir.join(PID_FILE));
+    println!("Exited with {}", status);
+}
+
+/// prints the lines of the server's output with a timestamp and appends them to the console log
+async fn tee<R: AsyncRead + Unpin>(output: R, log: Arc<Mutex<File>>, is_stderr: bool) {
+    let mut lines = BufReader::new(output).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let now = Local::now();
+        if is_stderr {
+            eprintln!("[{}] {}", now.format("%H:%M:%S"), line);
+        } else {
+            println!("[{}] {}", now.format("%H:%M:%S"), line);
+        }
+        let _ = writeln!(log.lock().unwrap(), "[{}] {}", now.format("%Y-%m-%d %H:%M:%S"), line);
+    }
+}
+
+pub fn stop(opt_name: Option<String>) {
+    let dir = server_dir(opt_name);
+    let Ok(pid) = fs::read_to_string(dir.join(PID_FILE)) else {
+        eprintln!("FATAL: The server isn't running.");
+        std::process::exit(-1);
+    };
+    let pid: u32 = pid.trim().parse().expect("Invalid pid file");
+
+    println!("Stopping server (pid {}), waiting up to {}s for the world to save...", pid, STOP_TIMEOUT.as_secs());
+    match process::stop(pid, STOP_TIMEOUT) {
+        true => println!("Stopped server (pid {})", pid),
+        false => {
+            eprintln!("FATAL: Failed to stop the server (pid {}).", pid);
+            let _ = fs::remove_file(dir.join(PID_FILE));
+            std::process::exit(-1);
+        },
+    }
+}
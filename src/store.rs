@@ -0,0 +1,66 @@
+use std::{error::Error, fs, io, path::{Path, PathBuf}};
+
+use directories::ProjectDirs;
+
+use crate::util;
+
+const BY_SHA1_DIR: &str = ".by-sha1";
+
+/// Libraries are downloaded once into a maven layout in the data dir, and hardlinked into the libs dir
+/// of every version using them
+pub fn store_dir() -> PathBuf {
+    let proj_dirs = ProjectDirs::from("me", "illia", "mc_cli").unwrap();
+    proj_dirs.data_dir().join("libraries")
+}
+
+/// where a library with this path and sha1 is stored. That's its maven path unless the file there has other bytes,
+/// which happens when versions list different builds under the same name, then it's kept under its sha1 instead
+/// so neither replaces the other
+pub fn stored_path(path: &str, sha1: Option<&str>) -> PathBuf {
+    let stored = store_dir().join(path);
+    match sha1 {
+        Some(sha1) if stored.is_file() && !util::file_matches_sha1(&stored, sha1) => conflict_path(path, sha1),
+        _ => stored,
+    }
+}
+
+/// where a library whose maven path holds other bytes is stored
+pub fn conflict_path(path: &str, sha1: &str) -> PathBuf {
+    let file_name = path.rsplit('/').next().unwrap();
+    store_dir().join(BY_SHA1_DIR).join(sha1.to_lowercase()).join(file_name)
+}
+
+/// whether the store has the library, with the right sha1 when the version JSON gives one
+pub fn has(stored: &Path, sha1: Option<&str>) -> bool {
+    match sha1 {
+        Some(sha1) => util::file_matches_sha1(stored, sha1),
+        None => stored.is_file(),
+    }
+}
+
+/// downloads a library to its path in the store. It's written next to it and renamed, so an interrupted download
+/// never looks finished and the versions linked to a file that gets replaced keep theirs.
+pub async fn download(url: &str, stored: &Path, sha1: Option<&str>) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(stored.parent().unwrap())?;
+    let mut partial = stored.as_os_str().to_owned();
+    partial.push(".part");
+    let partial = PathBuf::from(partial);
+
+    let data = util::download_async(url, &partial, "Downloaded lib".to_owned()).await?;
+    if let Some(sha1) = sha1 && !util::sha1_hex(&data).eq_ignore_ascii_case(sha1) {
+        let _ = fs::remove_file(&partial);
+        return Err(format!("{} doesn't match its sha1", url).into());
+    }
+    fs::rename(&partial, stored)?;
+    Ok(())
+}
+
+/// puts the stored library at `dest`, copying it when the two can't be hardlinked (like on another drive)
+pub fn link(stored: &Path, dest: &Path) -> io::Result<()> {
+    fs::create_dir_all(dest.parent().unwrap())?;
+    let _ = fs::remove_file(dest);
+    if fs::hard_link(stored, dest).is_err() {
+        fs::copy(stored, dest)?;
+    }
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use std::{error::Error, fs::{self, File}, io::{Read, Write}, path::{Path, PathBuf}};
+use std::{error::Error, fs::{self, File}, io::{Read, Write}, path::Path};
 use indicatif::{ProgressBar, ProgressStyle};
 use reqwest::blocking::Client;
 use futures_util::StreamExt as _;
@@ -216,19 +216,12 @@ pub async fn download_no_save_async(url: &str, msg: String) -> Result<Vec<u8>, B
     Ok(data)
 }
 
-pub fn list_files_recursively(dir: &Path) -> Vec<PathBuf> {
-    let mut files = Vec::new();
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir).unwrap() {
-            let entry = entry.unwrap();
-            let path = entry.path();
-            if path.is_dir() {
-                files.extend(list_files_recursively(&path));
-            } else {
-                files.push(path);
-            }
-        }
-    }
-    files
+pub fn sha1_hex(data: &[u8]) -> String {
+    use sha1::Digest as _;
+    hex::encode(sha1::Sha1::digest(data))
 }
 
+/// whether the file exists and has the given sha1
+pub fn file_matches_sha1(path: &Path, sha1: &str) -> bool {
+    fs::read(path).map(|data| sha1_hex(&data).eq_ignore_ascii_case(sha1)).unwrap_or(false)
+}
@@ -1,15 +1,20 @@
-use std::{collections::HashMap, fs, io::{BufRead, BufReader}, path::{Path, PathBuf}, process::{Command, Stdio}, sync::Arc};
+use std::{collections::{HashMap, HashSet, VecDeque}, error::Error, fs::{self, File}, io::{BufRead, BufReader, Read, Write as _}, path::{Path, PathBuf}, process::{Command, Stdio}, sync::{Arc, mpsc}, thread, time::SystemTime};
 
 use directories::ProjectDirs;
-use jars::JarOptionBuilder;
 use serde::Deserialize;
 use tokio::sync::Semaphore;
 use uuid::Uuid;
 
-use crate::{assets::AssetIndexJson, mem, rules, util, version::{self, VersionJson}};
+use crate::{app::LaunchOptions, assets::{self, AssetIndexJson}, crash, hooks::{self, HookContext}, instance, log4j, maven, mem, natives, ping, overrides, resolve::{self, ResolvedVersion}, rules, running, script, store, util, version::{self, Library, LibraryFile, LoggingConfig, VersionJson}};
 
-const VANILLA_MANIFEST: &'static str = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
+const VANILLA_MANIFEST: &str = "https://launchermeta.mojang.com/mc/game/version_manifest.json";
 const MAX_CONCURRENT_DOWNLOADS: i32 = 8;
+const CLASSPATH_SEPARATOR: &str = if cfg!(target_os = "windows") { ";" } else { ":" };
+/// how much of the output is kept to look for known issues when the game crashes without a report
+const CRASH_OUTPUT_LINES: usize = 200;
+const PLAYER_NAME: &str = "qwerty";
+const DEFAULT_WIDTH: u32 = 854;
+const DEFAULT_HEIGHT: u32 = 480;
 
 #[derive(Deserialize, Debug)]
 pub struct VanillaVersion {
@@ -52,7 +57,39 @@ pub async fn get_manifest() -> VanillaManifest {
     serde_json::from_str(&manifest_txt).unwrap()
 }
 
-pub fn launch(json: VersionJson, version_dir: PathBuf, limit: String) {
+/// The java command of a launch, along with the wrappers it runs through
+pub struct LaunchCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub current_dir: PathBuf,
+}
+
+impl LaunchCommand {
+    /// `java_args` are the arguments of java, every wrapper gets split into its program and arguments at whitespace
+    pub fn new(java_args: Vec<String>, wrappers: &[String], env: Vec<(String, String)>, current_dir: PathBuf) -> LaunchCommand {
+        let mut full = wrappers.iter().flat_map(|wrapper| wrapper.split_whitespace().map(str::to_owned)).collect::<Vec<_>>();
+        full.push("java".to_owned());
+        full.extend(java_args);
+        let program = full.remove(0);
+        LaunchCommand { program, args: full, env, current_dir }
+    }
+
+    /// the program followed by its arguments
+    pub fn full_args(&self) -> Vec<&str> {
+        std::iter::once(self.program.as_str()).chain(self.args.iter().map(String::as_str)).collect()
+    }
+
+    pub fn to_command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.current_dir(&self.current_dir).args(&self.args).envs(self.env.iter().cloned());
+        command
+    }
+}
+
+/// resolves every argument of the launch into the command to run, with the classpath joined by `classpath_separator`
+pub fn build_command(resolved: &ResolvedVersion, limit: &str, options: &LaunchOptions, classpath_separator: &str) -> LaunchCommand {
+    let version_dir = resolved.dir().to_path_buf();
     let game_dir = version_dir
         .parent()
         .unwrap()
@@ -65,23 +102,42 @@ pub fn launch(json: VersionJson, version_dir: PathBuf, limit: String) {
         .parent()
         .unwrap()
         .join("assets");
-    let libs = version_dir.join("libs");
-
-    let mut classpath_paths = util::list_files_recursively(&libs);
-    classpath_paths.push(version_dir.join("client.jar"));
-    let classpath = if std::env::consts::OS == "windows" {
-        classpath_paths
-            .iter()
-            .map(|e| e.to_string_lossy())
-            .collect::<Vec<_>>()
-            .join(";")
-    } else {
-        classpath_paths
-            .iter()
-            .map(|e| e.to_string_lossy())
-            .collect::<Vec<_>>()
-            .join(":")
-    };
+    let natives = natives::natives_dir(resolved);
+    // the only dir with every library of the chain in the maven layout, which is what module path arguments expect
+    let libraries = store::store_dir();
+    let assets_index_name = &resolved.json.assetIndex.id;
+    let game_assets = assets::game_assets_dir(&assets_dir, assets_index_name, &game_dir);
+
+    let classpath_paths = resolved.classpath().unwrap_or_else(|err| {
+        eprintln!("FATAL: {}", err);
+        std::process::exit(-1);
+    });
+    let classpath = classpath_paths
+        .iter()
+        .map(|e| e.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(classpath_separator);
+
+    let json = &resolved.json;
+    let custom_resolution = options.width.is_some() || options.height.is_some();
+    let width = options.width.unwrap_or(DEFAULT_WIDTH).to_string();
+    let height = options.height.unwrap_or(DEFAULT_HEIGHT).to_string();
+
+    let mut features: HashMap<String, bool> = HashMap::new();
+
+    let quick_play = supports_quick_play(json);
+    let quick_play_path = game_dir.join("quickPlay").join("java").join(format!("{}.json", version_dir.file_name().unwrap().to_string_lossy()));
+    let uses_quick_play = options.join.is_some() || options.world.is_some() || options.realm.is_some();
+    if uses_quick_play && quick_play {
+        let _ = fs::create_dir_all(quick_play_path.parent().unwrap());
+    }
+
+    features.insert("is_demo_user".to_owned(), options.demo);
+    features.insert("has_custom_resolution".to_owned(), custom_resolution);
+    features.insert("has_quick_plays_support".to_owned(), uses_quick_play && quick_play);
+    features.insert("is_quick_play_singleplayer".to_owned(), options.world.is_some());
+    features.insert("is_quick_play_multiplayer".to_owned(), options.join.is_some());
+    features.insert("is_quick_play_realms".to_owned(), options.realm.is_some());
 
     let mut jvm_args: Vec<String> = vec![format!("-Xmx{}", limit)];
 
@@ -90,16 +146,10 @@ pub fn launch(json: VersionJson, version_dir: PathBuf, limit: String) {
             match arg {
                 version::JvmArgument::String(arg) => jvm_args.push(arg),
                 version::JvmArgument::ArgWithRule { rules, value } => {
-                    for rule in &rules {
-                        if rules::matches_os_rule(rule) {
-                            match value {
-                                version::JvmArgumentValue::String(ref val) => {
-                                    jvm_args.push(val.clone());
-                                }
-                                version::JvmArgumentValue::Strings(ref vals) => {
-                                    jvm_args.extend(vals.clone());
-                                }
-                            }
+                    if rules::allowed(&rules, &features) {
+                        match value {
+                            version::JvmArgumentValue::String(val) => jvm_args.push(val),
+                            version::JvmArgumentValue::Strings(vals) => jvm_args.extend(vals),
                         }
                     }
                 }
@@ -118,41 +168,38 @@ pub fn launch(json: VersionJson, version_dir: PathBuf, limit: String) {
         ]);
     }
 
+    // only added when the config was downloaded and verified, the game logs plain text without it
+    if let Some(logging) = &json.logging.client {
+        let config = logging_config_path(&assets_dir, logging);
+        if util::file_matches_sha1(&config, &logging.file.sha1) {
+            jvm_args.push(logging.argument.replace("${path}", &config.to_string_lossy()));
+        }
+    }
+
     let jvm_args_resolved: Vec<String> = jvm_args
         .into_iter()
         .map(|arg| {
-            arg.replace("${natives_directory}", &libs.to_string_lossy())
+            arg.replace("${natives_directory}", &natives.to_string_lossy())
+                .replace("${library_directory}", &libraries.to_string_lossy())
+                .replace("${classpath_separator}", classpath_separator)
                 .replace("${classpath}", &classpath)
+                .replace("${version_name}", version_dir.file_name().unwrap().to_str().unwrap())
                 .replace("${launcher_name}", "mc_cli")
                 .replace("${launcher_version}", env!("CARGO_PKG_VERSION"))
         })
         .collect::<Vec<_>>();
 
     let mut game_args: Vec<String> = vec![];
-    let mut features: HashMap<String, bool> = HashMap::new();
-
-    features.insert("is_demo_user".to_owned(), false);
-    features.insert("has_custom_resolution".to_owned(), false);
-    features.insert("has_quick_plays_support".to_owned(), false);
-    features.insert("is_quick_play_singleplayer".to_owned(), false);
-    features.insert("is_quick_play_multiplayer".to_owned(), false);
-    features.insert("is_quick_play_realms".to_owned(), false);
 
     if let Some(arguments) = json.arguments.clone() {
         for arg in arguments.game {
             match arg {
                 version::GameArgument::String(arg) => game_args.push(arg),
                 version::GameArgument::ArgWithRule { rules, value } => {
-                    for rule in &rules {
-                        if rules::matches_arg_rule(features.clone(), rule) {
-                            match value {
-                                version::GameArgumentValue::String(ref val) => {
-                                    game_args.push(val.clone());
-                                }
-                                version::GameArgumentValue::Strings(ref vals) => {
-                                    game_args.extend(vals.clone());
-                                }
-                            }
+                    if rules::allowed(&rules, &features) {
+                        match value {
+                            version::GameArgumentValue::String(val) => game_args.push(val),
+                            version::GameArgumentValue::Strings(vals) => game_args.extend(vals),
                         }
                     }
                 }
@@ -161,12 +208,38 @@ pub fn launch(json: VersionJson, version_dir: PathBuf, limit: String) {
     } else if let Some(minecraft_arguments) = json.minecraftArguments.clone() {
         let args = minecraft_arguments.split(' ').map(|s| s.to_owned()).collect::<Vec<_>>();
         game_args.extend(args);
+
+        // the rules adding these only exist in the newer arguments format
+        if options.demo {
+            game_args.push("--demo".to_owned());
+        }
+        if custom_resolution {
+            game_args.extend(["--width".to_owned(), "${resolution_width}".to_owned(), "--height".to_owned(), "${resolution_height}".to_owned()]);
+        }
+    }
+
+    if options.fullscreen {
+        game_args.push("--fullscreen".to_owned());
+    }
+
+    // versions before quick play (1.20) can only join servers, through --server and --port
+    if !quick_play {
+        if let Some(server) = &options.join {
+            let (host, port) = ping::split_address(server).unwrap_or_else(|err| {
+                eprintln!("FATAL: Can't join {}: {}.", server, err);
+                std::process::exit(-1);
+            });
+            game_args.extend(["--server".to_owned(), host.to_owned(), "--port".to_owned(), port.unwrap_or(25565).to_string()]);
+        }
+        if options.world.is_some() || options.realm.is_some() {
+            eprintln!("Warning: {} doesn't support opening worlds or realms directly, ignoring", version_dir.file_name().unwrap().to_string_lossy());
+        }
     }
 
     let game_args_resolved: Vec<String> = game_args
         .into_iter()
         .map(|arg| {
-            arg.replace("${auth_player_name}", "qwerty")
+            arg.replace("${auth_player_name}", PLAYER_NAME)
                 .replace(
                     "${version_name}",
                     version_dir.file_name().unwrap().to_str().unwrap(),
@@ -174,44 +247,173 @@ pub fn launch(json: VersionJson, version_dir: PathBuf, limit: String) {
                 .replace("${game_directory}", game_dir.to_str().unwrap())
                 .replace("${auth_uuid}", &Uuid::new_v4().to_string())
                 .replace("${auth_access_token}", "")
+                .replace("${auth_session}", "")
                 .replace("${clientid}", &Uuid::new_v4().to_string())
                 .replace("${auth_xuid}", "0")
                 .replace("${user_type}", "offline")
                 .replace("${version_type}", &json.r#type)
                 .replace("${user_properties}", "{}")
-                .replace(
-                    "${assets_index_name}",
-                    &version_dir.file_name().unwrap().to_string_lossy(),
-                )
+                .replace("${assets_index_name}", assets_index_name)
                 .replace("${assets_root}", assets_dir.to_str().unwrap())
-                .replace("${game_assets}", assets_dir.to_str().unwrap())
+                .replace("${game_assets}", game_assets.to_str().unwrap())
+                .replace("${resolution_width}", &width)
+                .replace("${resolution_height}", &height)
+                .replace("${quickPlayPath}", quick_play_path.to_str().unwrap())
+                .replace("${quickPlaySingleplayer}", options.world.as_deref().unwrap_or_default())
+                .replace("${quickPlayMultiplayer}", options.join.as_deref().unwrap_or_default())
+                .replace("${quickPlayRealms}", options.realm.as_deref().unwrap_or_default())
         })
         .collect::<Vec<_>>();
 
     let mut cmd: Vec<String> = vec![];
     cmd.extend(jvm_args_resolved);
-    cmd.push(json.mainClass);
+    cmd.push(json.mainClass.clone());
     cmd.extend(game_args_resolved);
 
-    println!("cmd: {:?}", cmd);
+    LaunchCommand::new(cmd, &options.wrappers, options.env.clone(), game_dir)
+}
+
+pub fn launch(resolved: ResolvedVersion, limit: String, options: LaunchOptions) {
+    let options = instance::load(resolved.dir()).apply(options);
+    let command = build_command(&resolved, &limit, &options, CLASSPATH_SEPARATOR);
+
+    if options.dry_run {
+        script::print_dry_run(&command);
+        return;
+    }
+    if let Err(err) = natives::extract(&resolved) {
+        eprintln!("FATAL: Failed to extract natives: {}", err);
+        std::process::exit(-1);
+    }
+    if let Err(err) = assets::lay_out_legacy(&resolved.data_dir().join("assets"), &resolved.json.assetIndex.id, &command.current_dir) {
+        eprintln!("Warning: Failed to copy the assets to where this version looks for them: {}", err);
+    }
+    if !command.env.is_empty() {
+        println!("env: {:?}", command.env);
+    }
+    println!("cmd: {:?}", command.full_args());
+
+    let instance = resolved.dir().file_name().unwrap().to_string_lossy().to_string();
+    let game_version = resolved.root_dir().file_name().unwrap().to_string_lossy().to_string();
+    let game_dir = command.current_dir.clone();
+    if options.detach {
+        running::check_not_running(&instance);
+    }
+    let launch_log_path = running::new_log_path(&instance);
+    let mut hook_context = HookContext {
+        instance: &instance,
+        version: &game_version,
+        game_dir: &game_dir,
+        log: &launch_log_path,
+        exit_code: None,
+        crash_report: None,
+    };
+    if let Err(err) = hooks::run(&options.pre_launch, &hook_context) {
+        eprintln!("FATAL: {}, not launching.", err);
+        std::process::exit(-1);
+    }
+
+    if options.detach {
+        if !options.post_exit.is_empty() {
+            eprintln!("Warning: Post-exit hooks don't run for games in the background");
+        }
+        running::spawn_detached(&instance, PLAYER_NAME, &command, launch_log_path);
+        return;
+    }
 
+    let mut launch_log = File::create(&launch_log_path).expect("Failed to create launch log");
 
-    let mut process = Command::new("java")
-        .current_dir(game_dir)
-        .args(&cmd)
+    let started = SystemTime::now();
+    let mut process = command.to_command()
         .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
         .spawn()
         .expect("Failed to run Minecraft");
 
-    let stdout = process.stdout.take().expect("Failed to take stdout");
-    let reader = BufReader::new(stdout);
-    for line in reader.lines() {
-        let line = line.expect("Failed to read stdout line");
-        println!("{}", line);
+    // both streams are read at once so neither can fill up and block the game, and lines keep their order
+    let (lines, lines_rx) = mpsc::channel();
+    forward_lines(process.stdout.take().expect("Failed to take stdout"), false, lines.clone());
+    forward_lines(process.stderr.take().expect("Failed to take stderr"), true, lines);
+
+    let mut printer = log4j::Printer::new(options.log_level, options.log_file.as_deref());
+    let mut recent_output = VecDeque::new();
+    for (line, is_stderr) in lines_rx {
+        if is_stderr {
+            printer.push_stderr(&line);
+        } else {
+            printer.push(&line);
+        }
+        let _ = writeln!(launch_log, "{}", line);
+        if recent_output.len() == CRASH_OUTPUT_LINES {
+            recent_output.pop_front();
+        }
+        recent_output.push_back(line);
     }
+    printer.finish();
 
     let status = process.wait().expect("Failed to wait for child");
     println!("Exited with {}", status);
+
+    let summary = crash::check(&game_dir, started, status.success(), recent_output.make_contiguous());
+    if let Some(summary) = &summary {
+        summary.print();
+    }
+
+    hook_context.exit_code = status.code();
+    hook_context.crash_report = summary.as_ref().and_then(|summary| summary.report.as_deref());
+    if let Err(err) = hooks::run(&options.post_exit, &hook_context) {
+        eprintln!("Warning: {}", err);
+    }
+}
+
+/// sends the lines of the output to `lines` from a thread of its own
+fn forward_lines(output: impl Read + Send + 'static, is_stderr: bool, lines: mpsc::Sender<(String, bool)>) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(output);
+        let mut line = vec![];
+        // the game doesn't always write valid UTF-8, so lines aren't read as Strings
+        while reader.read_until(b'\n', &mut line).unwrap_or(0) > 0 {
+            let text = String::from_utf8_lossy(&line).trim_end_matches(['\n', '\r']).to_owned();
+            if lines.send((text, is_stderr)).is_err() {
+                break;
+            }
+            line.clear();
+        }
+    });
+}
+
+pub fn logging_config_path(assets_dir: &Path, logging: &LoggingConfig) -> PathBuf {
+    assets_dir.join("log_configs").join(&logging.file.id)
+}
+
+/// downloads the log4j config of the version into `assets/log_configs`, unless it's already there
+pub async fn download_logging_config(json: &VersionJson, assets_dir: &Path) {
+    let Some(logging) = &json.logging.client else { return };
+    let path = logging_config_path(assets_dir, logging);
+    if util::file_matches_sha1(&path, &logging.file.sha1) {
+        return;
+    }
+
+    let _ = fs::create_dir_all(path.parent().unwrap());
+    match util::download_async(&logging.file.url, &path, "Downloaded log config".to_owned()).await {
+        Ok(data) if util::sha1_hex(&data).eq_ignore_ascii_case(&logging.file.sha1) => {},
+        Ok(_) => {
+            let _ = fs::remove_file(&path);
+            eprintln!("Warning: The log config {} doesn't match its sha1, launching without it", logging.file.id);
+        },
+        Err(err) => eprintln!("Warning: Failed to download the log config {}: {}", logging.file.id, err),
+    }
+}
+
+/// whether the version has the quick play arguments added in 1.20
+pub fn supports_quick_play(json: &VersionJson) -> bool {
+    let Some(arguments) = &json.arguments else { return false };
+    arguments.game.iter().any(|arg| match arg {
+        version::GameArgument::ArgWithRule { rules, .. } => rules.iter().any(|rule| {
+            rule.features.as_ref().is_some_and(|features| features.0.contains_key("is_quick_play_multiplayer"))
+        }),
+        version::GameArgument::String(_) => false,
+    })
 }
 
 pub fn create_dirs(vers: PathBuf, ver: PathBuf) {
@@ -222,7 +424,7 @@ pub fn create_dirs(vers: PathBuf, ver: PathBuf) {
     let _ = fs::create_dir(vers.parent().unwrap().join("assets"));
 }
 
-pub async fn handle(opt_version: Option<String>, limit: String, b_launch: bool, version_dir: Option<&Path>) {
+pub async fn handle(opt_version: Option<String>, limit: String, b_launch: bool, options: LaunchOptions) {
     mem::check_if_valid(limit.clone());
 
     let manifest = get_manifest().await;
@@ -231,35 +433,85 @@ pub async fn handle(opt_version: Option<String>, limit: String, b_launch: bool,
     let proj_dirs = ProjectDirs::from("me", "illia", "mc_cli").unwrap();
     let data_dir = proj_dirs.data_dir();
     let vers = data_dir.join("vers");
-    let binding = vers.join(version.as_str());
-    let ver = version_dir.unwrap_or(&binding);
-    let libs = ver.join("libs");
+    let ver = vers.join(version.as_str());
 
-    'launch_logic: {
-        if ver.is_dir() {
-            println!("Launching vanilla {} with memory limit {}", version, limit);
+    if !ver.join("version.json").is_file() {
+        install(manifest, version.clone(), vers, ver.clone()).await;
+    }
 
-            let text = fs::read_to_string(ver.join("version.json"));
-            if !text.is_ok() {
-                break 'launch_logic;
-            }
-            let text = text.unwrap();
-            let mut version_json_err = serde_json::Deserializer::from_str(&text);
-            let version_json_res = serde_path_to_error::deserialize::<_, VersionJson>(&mut version_json_err);
-            let version_json = match version_json_res {
-                Ok(val) => val,
-                Err(err) => panic!("err: {:#?}", err),
-            };
-
-            if b_launch {
-                launch(version_json, ver.to_path_buf(), limit.clone());
+    if b_launch {
+        println!("Launching vanilla {} with memory limit {}", version, limit);
+
+        let resolved = match resolve::resolve(&ver) {
+            Ok(val) => val,
+            Err(err) => panic!("err: {:#?}", err),
+        };
+        download_logging_config(&resolved.json, &data_dir.join("assets")).await;
+        launch(resolved, limit, options);
+    }
+}
+
+/// puts the libraries into `libs`, natives jars included. They're downloaded into the shared store
+/// unless it already has them, and linked from there. The error lists every library that couldn't be.
+pub async fn download_libraries(libraries: &[Library], libs: &Path) -> Result<(), Box<dyn Error>> {
+    let lib_semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_DOWNLOADS as usize));
+
+    let mut download_tasks = Vec::new();
+    let mut seen = HashSet::new();
+
+    for lib in &overrides::apply(libraries) {
+        if !rules::library_allowed(lib) {
+            continue;
+        }
+
+        for LibraryFile { path, url: opt_url, sha1 } in lib.files()? {
+            let download_path = libs.join(&path);
+            if download_path.exists() || !seen.insert(path.clone()) {
+                continue;
             }
+            let stored = store::stored_path(&path, sha1.as_deref());
+            let sem = Arc::clone(&lib_semaphore);
+            let name = lib.name.clone();
+            let coordinate = lib.coordinate();
+            let repositories = maven::repositories(lib.url.as_deref());
+
+            download_tasks.push(tokio::spawn(async move {
+                let _permit = sem.acquire().await.unwrap();
 
-            return;
+                if !store::has(&stored, sha1.as_deref()) {
+                    let url = match opt_url {
+                        Some(url) => url,
+                        // loader libraries only give a maven name, and maybe the repository they're in
+                        None => maven::find(&coordinate?, &repositories).await.map_err(|err| err.to_string())?,
+                    };
+                    store::download(&url, &stored, sha1.as_deref()).await.map_err(|err| format!("Failed to download {}: {}", name, err))?;
+                }
+                store::link(&stored, &download_path).map_err(|err| format!("Failed to link {}: {}", name, err))
+            }));
         }
     }
 
-    create_dirs(vers, ver.to_path_buf());
+    // Wait for all downloads to complete
+    let errors = futures_util::future::join_all(download_tasks)
+        .await
+        .into_iter()
+        .filter_map(|result| match result {
+            Ok(Ok(())) => None,
+            Ok(Err(err)) => Some(err),
+            Err(err) => Some(err.to_string()),
+        })
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        return Err(errors.join("\n").into());
+    }
+    Ok(())
+}
+
+pub async fn install(manifest: VanillaManifest, version: String, vers: PathBuf, ver: PathBuf) {
+    let data_dir = vers.parent().unwrap().to_path_buf();
+    let libs = ver.join("libs");
+
+    create_dirs(vers, ver.clone());
 
     let ver_url = get_ver_json_url(manifest, version.clone());
 
@@ -275,120 +527,66 @@ pub async fn handle(opt_version: Option<String>, limit: String, b_launch: bool,
     // download minecraft jar
     let client_url = version_json.downloads.client.url.clone();
     let _ = util::download_async(client_url.as_str(), ver.join("client.jar").as_path(), "Downloaded client jar".to_owned()).await.expect("Failed to download client jar");
-    let lib_semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_DOWNLOADS as usize));
-
-    let mut download_tasks = Vec::new();
-
-    for lib in &version_json.libraries {
-        let should_download = if let Some(rules) = &lib.rules {
-            rules.iter().any(rules::matches_os_rule)
-        } else {
-            true
-        };
 
-        if should_download && lib.downloads.artifact.is_some() {
-            let artifact = lib.downloads.artifact.as_ref().unwrap();
-            let path = Path::new(&artifact.path);
-            let dir_path = libs.join(path.parent().unwrap());
-            let download_path = libs.join(path);
-            let url = artifact.url.clone();
-            let sem = Arc::clone(&lib_semaphore);
-
-            tokio::fs::create_dir_all(&dir_path).await.unwrap_or_default();
-
-            download_tasks.push(tokio::spawn(async move {
-                let _permit = sem.acquire().await.unwrap();
-
-                util::download_async(
-                    &url, 
-                    &download_path, 
-                    "Downloaded lib".to_owned()
-                ).await.expect("Failed to download library")
-            }));
-        }
-
-        if let Some(classifiers) = &lib.downloads.classifiers {
-            let needed = rules::classifiers_needed(classifiers);
-
-            for needed_classifier in needed {
-                let path = Path::new(&needed_classifier.path);
-                let dir_path = libs.join(path.parent().unwrap());
-                let download_path = libs.join(path);
-                let url = needed_classifier.url.clone();
-                let libs_clone = libs.to_path_buf();
-                let extract = lib.extract.clone();
-
-                tokio::fs::create_dir_all(&dir_path).await.unwrap_or_default();
-
-                download_tasks.push(tokio::spawn(async move {
-                    let classifier_lib = util::download_async(
-                        &url,
-                        &download_path,
-                        "Downloaded classifier lib".to_owned()
-                    ).await.expect("Failed to download classifier lib");
-
-                    if let Some(extract_info) = extract {
-                        let option = JarOptionBuilder::builder().target(libs_clone.to_str().unwrap()).build();
-                        println!("lib: {:#?}", download_path);
-                        let jar = jars::jar(download_path, option).expect("Failed to extract library jar file");
-
-                        for (file_path, content) in jar.files {
-                            let dir = if Path::new(&file_path).is_dir() {
-                                Path::new(&file_path)
-                            } else {
-                                Path::new(&file_path).parent().unwrap()
-                            };
-
-                            tokio::fs::create_dir_all(libs_clone.join(dir)).await
-                                .expect("Failed to create directory for extracted file");
-
-                            tokio::fs::write(libs_clone.join(&file_path), content).await
-                                .expect(&format!("Failed to write extracted file {}", file_path));
-                            }
-
-                        for excluded in extract_info.exclude {
-                            let _ = tokio::fs::remove_file(excluded).await;
-                        }
-                    }
-                    classifier_lib
-                }));
-            }
-        }
+    if let Err(err) = download_libraries(&version_json.libraries, &libs).await {
+        // without its version.json the version gets installed again next time
+        let _ = fs::remove_file(ver.join("version.json"));
+        eprintln!("FATAL: Failed to download the libraries of {}:\n{}", version, err);
+        std::process::exit(-1);
     }
 
-    // Wait for all downloads to complete
-    futures_util::future::join_all(download_tasks).await;
-
     let assets_dir = data_dir.join("assets");
+    download_logging_config(&version_json, &assets_dir).await;
     let _ = fs::create_dir(assets_dir.join("indexes"));
 
-    let asset_index_url = version_json.assetIndex.url.clone();
-    let asset_index = util::download_text_async(&asset_index_url, &assets_dir.join("indexes").join(format!("{}.json", version)), "Downloaded asset index".to_owned()).await.expect("Failed to download asset index json");
+    // versions with the same asset index share it, so it's only downloaded again if it changed
+    let index_path = assets::index_path(&assets_dir, &version_json.assetIndex.id);
+    let asset_index = if util::file_matches_sha1(&index_path, &version_json.assetIndex.sha1) {
+        fs::read_to_string(&index_path).expect("Failed to read asset index json")
+    } else {
+        let text = util::download_text_async(&version_json.assetIndex.url, &index_path, "Downloaded asset index".to_owned()).await.expect("Failed to download asset index json");
+        if !util::sha1_hex(text.as_bytes()).eq_ignore_ascii_case(&version_json.assetIndex.sha1) {
+            let _ = fs::remove_file(&index_path);
+            eprintln!("FATAL: The downloaded asset index {} doesn't match its sha1.", version_json.assetIndex.id);
+            std::process::exit(-1);
+        }
+        text
+    };
 
     let asset_index_json: AssetIndexJson = serde_json::from_str(&asset_index).expect("Failed to parse asset index json");
-    let assets = asset_index_json.objects;
+    // several names can point at the same object
+    let mut seen = HashSet::new();
+    let missing = asset_index_json
+        .objects
+        .values()
+        .filter(|object| seen.insert(object.hash.clone()) && !assets::has_object(&assets_dir, object))
+        .map(|object| object.hash.clone())
+        .collect::<Vec<_>>();
+    println!("{} of {} assets are already downloaded", seen.len() - missing.len(), seen.len());
 
     let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_DOWNLOADS as usize));
 
-    let download_futures = assets.iter().map(|asset| {
-        let hash = asset.1.hash.clone();
+    let download_futures = missing.into_iter().map(|hash| {
         let assets_dir = assets_dir.clone();
         let sem = Arc::clone(&semaphore);
 
         async move {
             let _permit = sem.acquire().await.unwrap();
 
-            let dir = &hash[..2];
-            let dir_full = assets_dir.join("objects").join(dir);
-            let _ = fs::create_dir_all(&dir_full);
+            let destination = assets::object_path(&assets_dir, &hash);
+            let _ = fs::create_dir_all(destination.parent().unwrap());
 
-            let url = format!("https://resources.download.minecraft.net/{}/{}", dir, hash);
-            let destination = dir_full.join(&hash);
+            let url = format!("https://resources.download.minecraft.net/{}/{}", &hash[..2], hash);
 
             match tokio::time::timeout(
                 tokio::time::Duration::from_secs(60),
                 util::download_async(&url, &destination, "Downloaded resource".to_owned())
             ).await {
+                Ok(Ok(data)) if !util::sha1_hex(&data).eq_ignore_ascii_case(&hash) => {
+                    let _ = fs::remove_file(&destination);
+                    eprintln!("Downloaded resource {} doesn't match its hash", hash);
+                    Err(format!("Hash mismatch: {}", hash))
+                },
                 Ok(Ok(_)) => {
                     println!("Successfully downloaded resource {}", hash);
                     Ok(hash)
@@ -406,8 +604,4 @@ pub async fn handle(opt_version: Option<String>, limit: String, b_launch: bool,
     }).collect::<Vec<_>>();
 
     futures_util::future::join_all(download_futures).await;
-
-    if b_launch {
-        launch(version_json, ver.to_path_buf(), limit.clone());
-    }
 }
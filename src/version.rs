@@ -2,9 +2,13 @@ use std::collections::HashMap;
 
 use serde::Deserialize;
 
+use crate::{maven::Coordinate, rules};
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct Arguments {
+    #[serde(default)]
     pub game: Vec<GameArgument>,
+    #[serde(default)]
     pub jvm: Vec<JvmArgument>,
 }
 
@@ -15,7 +19,7 @@ pub struct Download {
     pub url: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct LibraryDownload {
     pub path: String,
     pub sha1: String,
@@ -31,7 +35,7 @@ pub struct Downloads {
     pub server_mappings: Option<Download>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct LibraryClassifiers {
     pub natives_windows_64: Option<LibraryDownload>,
@@ -45,19 +49,19 @@ pub struct LibraryClassifiers {
     pub natives_linux_64: Option<LibraryDownload>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct LibraryDownloads {
     pub artifact: Option<LibraryDownload>,
     pub classifiers: Option<LibraryClassifiers>,
 }
 
+/// every condition that is set has to match
 #[derive(Deserialize, Debug, Clone)]
-#[serde(untagged)]
-pub enum Os {
-    Name { name: String },
-    Arch { arch: String},
-    Both { name: String, arch: String },
-    // pub name: String,
+pub struct Os {
+    pub name: Option<String>,
+    pub arch: Option<String>,
+    /// a regex matched against the OS version, like `^10\\.` for Windows 10
+    pub version: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -71,19 +75,86 @@ pub struct AssetIndex {
     pub url: String,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct LoggingFile {
+    pub id: String,
+    pub sha1: String,
+    pub size: i32,
+    pub url: String,
+}
+
+/// the log4j config Mojang wants the game to run with
+#[derive(Deserialize, Debug)]
+pub struct LoggingConfig {
+    /// contains `${path}`, which is where the file got saved
+    pub argument: String,
+    pub file: LoggingFile,
+    pub r#type: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Logging {
+    pub client: Option<LoggingConfig>,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct Extract {
     pub exclude: Vec<String>
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct Library {
+    // loader libraries (fabric, quilt, liteloader...) only give a maven name and repository url
+    #[serde(default)]
     pub downloads: LibraryDownloads,
     pub name: String,
+    pub url: Option<String>,
     pub rules: Option<Vec<Rule>>,
     pub extract: Option<Extract>,
 }
 
+impl Library {
+    pub fn coordinate(&self) -> Result<Coordinate, String> {
+        self.name.parse()
+    }
+
+    /// every file of the library this platform needs, natives included
+    pub fn files(&self) -> Result<Vec<LibraryFile>, String> {
+        let mut files = vec![];
+        if let Some((path, url)) = self.artifact()? {
+            let sha1 = self.downloads.artifact.as_ref().map(|artifact| artifact.sha1.clone());
+            files.push(LibraryFile { path, url, sha1 });
+        }
+        if let Some(classifiers) = &self.downloads.classifiers {
+            for download in rules::classifiers_needed(classifiers) {
+                files.push(LibraryFile { path: download.path.clone(), url: Some(download.url.clone()), sha1: Some(download.sha1.clone()) });
+            }
+        }
+        Ok(files)
+    }
+
+    /// returns the path of the main artifact relative to the libs dir, and the url to download it from if the
+    /// version JSON gives one, otherwise it has to be looked up in the repositories
+    pub fn artifact(&self) -> Result<Option<(String, Option<String>)>, String> {
+        if let Some(artifact) = &self.downloads.artifact {
+            return Ok(Some((artifact.path.clone(), Some(artifact.url.clone()))));
+        }
+        if self.downloads.classifiers.is_some() {
+            // natives-only library
+            return Ok(None);
+        }
+        Ok(Some((self.coordinate()?.path(), None)))
+    }
+}
+
+/// A file of a library, the url and sha1 are only known when the version JSON gives them
+pub struct LibraryFile {
+    /// relative to the libs dir
+    pub path: String,
+    pub url: Option<String>,
+    pub sha1: Option<String>,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct FabricLib {
     pub name: String,
@@ -94,13 +165,19 @@ pub struct FabricLib {
 // because serde doesn't wanna rename it for me!
 #[allow(non_snake_case)]
 pub struct VersionJson {
+    pub id: String,
+    pub inheritsFrom: Option<String>,
+    pub jar: Option<String>,
     pub arguments: Option<Arguments>,
     pub minecraftArguments: Option<String>,
     pub downloads: Downloads,
+    #[serde(default)]
     pub libraries: Vec<Library>,
     pub mainClass: String,
     pub r#type: String,
     pub assetIndex: AssetIndex,
+    #[serde(default)]
+    pub logging: Logging,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -154,22 +231,6 @@ pub struct FabricLoaderVersion {
     pub version: String,
 }
 
-pub fn maven_to_path(coords: String) -> String {
-    let parts: Vec<&str> = coords.split(':').collect();
-
-    if parts.len() != 3 {
-        panic!("Invalid Maven coordinates, expected format 'groupId:artifactId:version'");
-    }
-
-    let group_id = parts[0];
-    let artifact_id = parts[1];
-    let version = parts[2];
-
-    let group_path = group_id.replace('.', "/");
-
-    format!("{}/{}/{}/{}-{}.jar", group_path, artifact_id, version, artifact_id, version)
-}
-
 impl FabricLoaderVersion {
     pub fn split(&self) -> (&str, &str) {
         let arr = self.version.split(&self.separator).collect::<Vec<&str>>();
@@ -193,7 +254,9 @@ impl FabricLoaderVersion {
 #[derive(Deserialize, Debug, Clone)]
 #[allow(non_snake_case)]
 pub struct FabricLoaderJSON {
+    #[serde(default)]
     pub version: i32,
+    #[serde(default)]
     pub min_java_version: i32,
     pub libraries: FabricLibraries,
     pub mainClass: FabricMainClass,
@@ -201,9 +264,13 @@ pub struct FabricLoaderJSON {
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct FabricLibraries {
+    #[serde(default)]
     pub client: Vec<FabricLib>,
+    #[serde(default)]
     pub common: Vec<FabricLib>,
+    #[serde(default)]
     pub server: Vec<FabricLib>,
+    #[serde(default)]
     pub development: Vec<FabricLib>,
 }
 
@@ -225,6 +292,7 @@ pub enum UseQuilt {
 }
 
 #[derive(Deserialize, Debug, Clone)]
+#[allow(non_snake_case)]
 pub struct LiteLoaderMeta {
     pub description: String,
     pub authors: String,
@@ -242,6 +310,7 @@ pub struct LiteLoaderRepo {
 }
 
 #[derive(Deserialize, Debug, Clone)]
+#[allow(non_snake_case)]
 pub struct LiteLoaderTweaks {
     pub tweakClass: String,
     pub libraries: Vec<LiteLoaderLibrary>,
@@ -275,6 +344,7 @@ pub struct LiteLoaderVersion {
 }
 
 #[derive(Deserialize, Debug, Clone)]
+#[allow(non_snake_case)]
 pub struct LiteLoaderArtifacts {
     pub tweakClass: String,
     pub libraries: Vec<LiteLoaderLibrary>,
@@ -298,8 +368,10 @@ pub struct LiteLoaderVersions {
 }
 
 #[derive(Deserialize, Debug, Clone)]
+#[allow(non_snake_case)]
 pub struct MavenMetadataVersioning {
-    pub snapshot: MavenMetadataSnapshot,
+    // only the metadata of a snapshot version has it
+    pub snapshot: Option<MavenMetadataSnapshot>,
     pub lastUpdated: String,
 }
 